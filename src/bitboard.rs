@@ -1,3 +1,6 @@
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
 use board::*;
 use uci::in_millis;
 
@@ -7,7 +10,10 @@ pub fn initialize() {
         let t1 = ::std::time::Instant::now();
         init_simple_bitboards();
         init_mundane_attacks();
-        init_magic();
+        // The slider tables themselves are baked in at build time (see
+        // build.rs); all that's left at startup is picking which of the two
+        // baked-in layouts (PEXT or fancy-magic) this CPU can use.
+        unsafe { use_pext = pext_available(); }
         init_pseudo_attacks();
         init_post_attack_bitboards();
         init_king_safety();
@@ -15,8 +21,188 @@ pub fn initialize() {
     })
 }
 
-// TODO: look into performance implications of unchecked indexing
-pub type Bitboard = u64;
+// A set of squares, one bit per square with A1 as bit 0 and H8 as bit 63.
+// Wrapping this in a newtype (rather than a bare u64) lets us give it real
+// operators plus a `Square` iterator, so movegen-style code can write
+// `for sq in attacks { ... }` instead of hand-rolled `while b != 0` loops
+// around `pop_square`. Modeled after how shakmaty represents square sets.
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn more_than_one(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    pub fn single_square(self) -> Option<Square> {
+        if self.is_empty() || self.more_than_one() {
+            None
+        } else {
+            Some(lsb(self))
+        }
+    }
+
+    pub fn contains(self, sq: Square) -> bool {
+        !(self & bb(sq)).is_empty()
+    }
+
+    pub fn wrapping_sub(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0.wrapping_sub(rhs.0))
+    }
+
+    pub fn wrapping_mul(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0.wrapping_mul(rhs.0))
+    }
+
+    // Parses a hex literal, with or without a leading "0x"/"0X".
+    pub fn from_hex(s: &str) -> Option<Bitboard> {
+        let digits = s.trim_start_matches("0x").trim_start_matches("0X");
+        u64::from_str_radix(digits, 16).ok().map(Bitboard)
+    }
+
+    // Parses a Rust-style integer literal: "0x.."/"0o.."/"0b.." select the
+    // radix, otherwise the string is read as decimal.
+    pub fn from_bits_str(s: &str) -> Option<Bitboard> {
+        let (radix, digits) = if s.starts_with("0x") || s.starts_with("0X") {
+            (16, &s[2..])
+        } else if s.starts_with("0o") || s.starts_with("0O") {
+            (8, &s[2..])
+        } else if s.starts_with("0b") || s.starts_with("0B") {
+            (2, &s[2..])
+        } else {
+            (10, s)
+        };
+        u64::from_str_radix(digits, radix).ok().map(Bitboard)
+    }
+}
+
+// Eight rows of '.'/'X', rank 8 at top, for quick eyeballing in test
+// failures and debug prints.
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in each_rank().rev() {
+            for file in each_file() {
+                write!(f, "{}", if self.contains(sq(file, r)) { "X" } else { "." })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// `{:#x}` prints the canonical grouped form (0x1234_5678_9abc_def0); plain
+// `{:x}` falls back to the bare hex digits.
+impl fmt::LowerHex for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f,
+                    "0x{:04x}_{:04x}_{:04x}_{:04x}",
+                    (self.0 >> 48) & 0xffff,
+                    (self.0 >> 32) & 0xffff,
+                    (self.0 >> 16) & 0xffff,
+                    self.0 & 0xffff)
+        } else {
+            write!(f, "{:x}", self.0)
+        }
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+    fn bitxor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+    fn shl(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 << rhs)
+    }
+}
+
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+    fn shr(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 >> rhs)
+    }
+}
+
+pub struct BitboardIter(u64);
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            let sq = Square::from_u8(self.0.trailing_zeros() as u8);
+            self.0 &= self.0 - 1;
+            Some(sq)
+        }
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+    fn into_iter(self) -> BitboardIter {
+        BitboardIter(self.0)
+    }
+}
 
 pub trait IntoBitboard {
     fn into_bitboard(self) -> Bitboard;
@@ -38,7 +224,7 @@ impl IntoBitboard for File {
 
 impl IntoBitboard for Square {
     fn into_bitboard(self) -> Bitboard {
-        1 << self.index()
+        Bitboard(1 << self.index())
     }
 }
 
@@ -50,7 +236,7 @@ pub fn bb_to_str(b: Bitboard) -> String {
     let mut s = String::from("\n");
     for r in each_rank().rev() {
         for f in each_file() {
-            if b & bb(sq(f, r)) != 0 {
+            if b.contains(sq(f, r)) {
                 s.push('x');
             } else {
                 s.push('.');
@@ -62,7 +248,7 @@ pub fn bb_to_str(b: Bitboard) -> String {
 }
 
 pub fn bb_from_str(s: &str) -> Bitboard {
-    let mut b: Bitboard = 0;
+    let mut b = Bitboard(0);
     let lines: Vec<&str> = s.split_whitespace().collect();
     for sq in each_square() {
         if lines[7 - sq.rank().index()].as_bytes()[sq.file().index()] == 'x' as u8 {
@@ -113,33 +299,34 @@ pub fn shift(b: Bitboard, d: Delta) -> Bitboard {
 }
 
 pub fn lsb(b: Bitboard) -> Square {
-    debug_assert!(b != 0);
-    Square::from_u8(b.trailing_zeros() as u8)
+    debug_assert!(!b.is_empty());
+    Square::from_u8(b.0.trailing_zeros() as u8)
 }
 
 pub fn pop_square(b: &mut Bitboard) -> Square {
     let sq = lsb(*b);
-    *b &= *b - 1;
+    b.0 &= b.0 - 1;
     sq
 }
 
 
-static mut rank_bb: [Bitboard; 8] = [0; 8];
-static mut file_bb: [Bitboard; 8] = [0; 8];
+static mut rank_bb: [Bitboard; 8] = [Bitboard(0); 8];
+static mut file_bb: [Bitboard; 8] = [Bitboard(0); 8];
 static mut distance: [[u8; 64]; 64] = [[0; 64]; 64];
+static mut distance_ring_bb: [[Bitboard; 8]; 64] = [[Bitboard(0); 8]; 64];
 
-static mut neighbor_files_bb: [Bitboard; 8] = [0; 8];
-static mut in_front_bb: [[Bitboard; 64]; 2] = [[0; 64]; 2];
-static mut passer_bb: [[Bitboard; 64]; 2] = [[0; 64]; 2];
-static mut outpost_bb: [[Bitboard; 64]; 2] = [[0; 64]; 2];
+static mut neighbor_files_bb: [Bitboard; 8] = [Bitboard(0); 8];
+static mut in_front_bb: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
+static mut passer_bb: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
+static mut outpost_bb: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
 
-static mut squares_of_color_bb: [Bitboard; 2] = [0; 2];
+static mut squares_of_color_bb: [Bitboard; 2] = [Bitboard(0); 2];
 
 fn init_simple_bitboards() {
     for i in 0..8 {
         unsafe {
-            rank_bb[i] = 0xff << (8 * i);
-            file_bb[i] = 0x0101010101010101 << i;
+            rank_bb[i] = Bitboard(0xff << (8 * i));
+            file_bb[i] = Bitboard(0x0101010101010101 << i);
         }
     }
     for i in 0..8 {
@@ -168,7 +355,7 @@ fn init_simple_bitboards() {
             }
             let near_files = this_file | neighbor_files;
 
-            passer_bb[0][i] &= near_files; 
+            passer_bb[0][i] &= near_files;
             in_front_bb[0][i] = passer_bb[0][i] & this_file;
             outpost_bb[0][i] = passer_bb[0][i] & neighbor_files;
 
@@ -182,6 +369,7 @@ fn init_simple_bitboards() {
             let fd = sq1.file() as i8 - sq2.file() as i8;
             unsafe {
                 distance[i][j] = ::std::cmp::max(rd.abs(), fd.abs()) as u8;
+                distance_ring_bb[i][distance[i][j] as usize] |= bb(sq2);
             }
         }
     }
@@ -216,10 +404,18 @@ pub fn dist(sq1: Square, sq2: Square) -> u8 {
     unsafe { distance[sq1.index()][sq2.index()] }
 }
 
-static mut white_pawn_attacks_bb: [Bitboard; 64] = [0; 64];
-static mut black_pawn_attacks_bb: [Bitboard; 64] = [0; 64];
-static mut knight_attacks_bb: [Bitboard; 64] = [0; 64];
-static mut king_attacks_bb: [Bitboard; 64] = [0; 64];
+// All squares at exactly Chebyshev distance `d` from `sq`. Useful for
+// king-tropism terms and for scanning "attacks near the enemy king" zones
+// ring by ring instead of re-scanning all 64 squares.
+pub fn distance_ring(sq: Square, d: u8) -> Bitboard {
+    debug_assert!(sq != Square::NoSquare && (d as usize) < 8);
+    unsafe { distance_ring_bb[sq.index()][d as usize] }
+}
+
+static mut white_pawn_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
+static mut black_pawn_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
+static mut knight_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
+static mut king_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
 
 fn init_mundane(attacks_bb: &mut [Bitboard; 64], deltas: &[Delta]) {
     for sq1 in each_square() {
@@ -252,9 +448,9 @@ fn init_mundane_attacks() {
     }
 }
 
-static mut king_near_shield_bb: [[Bitboard; 64]; 2] = [[0; 64]; 2];
-static mut king_shield_bb: [[Bitboard; 64]; 2] = [[0; 64]; 2];
-static mut king_halo_bb: [Bitboard; 64] = [0; 64];
+static mut king_near_shield_bb: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
+static mut king_shield_bb: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
+static mut king_halo_bb: [Bitboard; 64] = [Bitboard(0); 64];
 
 fn init_king_safety() {
     unsafe {
@@ -299,40 +495,70 @@ pub fn king_halo(sq: Square) -> Bitboard {
     unsafe { king_halo_bb[sq.index()] }
 }
 
-static mut bishop_masks: [Bitboard; 64] = [0; 64];
-static mut bishop_magic: [Bitboard; 64] = [0; 64];
-static mut bishop_attacks_bb: [[Bitboard; 512]; 64] = [[0; 512]; 64];
+// The masks, magics, shifts, offsets, and flattened attack tables below are
+// generated at build time by build.rs, which runs the same trial-and-error
+// magic search this module used to run on every startup. Baking the result
+// in as `static` data means the slider tables are plain immutable arrays: no
+// `unsafe static mut`, no init-time search, no data race to guard against.
+include!(concat!(env!("OUT_DIR"), "/magic_tables.rs"));
 
-static mut rook_masks: [Bitboard; 64] = [0; 64];
-static mut rook_magic: [Bitboard; 64] = [0; 64];
-static mut rook_attacks_bb: [[Bitboard; 4096]; 64] = [[0; 4096]; 64];
+// Set once at init time to whichever backend is faster on this CPU. We check
+// the feature once and cache the answer rather than calling
+// `is_x86_feature_detected!` on every probe. Both backends' tables are baked
+// in regardless of which one this machine ends up using, since the build
+// machine and the run machine may differ.
+static mut use_pext: bool = false;
+
+#[cfg(target_arch = "x86_64")]
+fn pext_available() -> bool {
+    is_x86_feature_detected!("bmi2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn pext_available() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn pext(occ: Bitboard, mask: Bitboard) -> usize {
+    ::std::arch::x86_64::_pext_u64(occ.0, mask.0) as usize
+}
 
-fn magic_bishop_index(sq: Square, mut occ: Bitboard) -> usize {
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn pext(_occ: Bitboard, _mask: Bitboard) -> usize {
+    unreachable!("pext backend selected on a non-x86_64 target");
+}
+
+fn pext_bishop_index(sq: Square, occ: Bitboard) -> usize {
     debug_assert!(sq != Square::NoSquare);
-    unsafe {
-        occ &= bishop_masks[sq.index()];
-        occ = occ.wrapping_mul(bishop_magic[sq.index()]);
-    }
-    (occ >> 55) as usize
+    unsafe { BISHOP_PEXT_BASE[sq.index()] + pext(occ, Bitboard(BISHOP_MASKS[sq.index()])) }
 }
 
-fn magic_rook_index(sq: Square, mut occ: Bitboard) -> usize {
+fn pext_rook_index(sq: Square, occ: Bitboard) -> usize {
     debug_assert!(sq != Square::NoSquare);
-    unsafe {
-        occ &= rook_masks[sq.index()];
-        occ = occ.wrapping_mul(rook_magic[sq.index()]);
-    }
-    (occ >> 52) as usize
+    unsafe { ROOK_PEXT_BASE[sq.index()] + pext(occ, Bitboard(ROOK_MASKS[sq.index()])) }
+}
+
+fn magic_bishop_index(sq: Square, occ: Bitboard) -> usize {
+    debug_assert!(sq != Square::NoSquare);
+    let idx = (occ.0 & BISHOP_MASKS[sq.index()]).wrapping_mul(BISHOP_MAGICS[sq.index()]);
+    BISHOP_OFFSETS[sq.index()] + (idx >> BISHOP_SHIFTS[sq.index()]) as usize
+}
+
+fn magic_rook_index(sq: Square, occ: Bitboard) -> usize {
+    debug_assert!(sq != Square::NoSquare);
+    let idx = (occ.0 & ROOK_MASKS[sq.index()]).wrapping_mul(ROOK_MAGICS[sq.index()]);
+    ROOK_OFFSETS[sq.index()] + (idx >> ROOK_SHIFTS[sq.index()]) as usize
 }
 
 fn slide_mask(sq: Square, occ: Bitboard, deltas: &[Delta]) -> Bitboard {
     debug_assert!(sq != Square::NoSquare);
-    let mut mask: Bitboard = 0;
+    let mut mask = Bitboard(0);
     for d in deltas.iter() {
         let (mut sq2, mut old_sq2) = (sq, sq);
         while sq2 != Square::NoSquare && dist(sq2, old_sq2) <= 1 {
             mask |= bb(sq2);
-            if bb(sq2) & occ != 0 {
+            if (bb(sq2) & occ) != Bitboard(0) {
                 break;
             }
             old_sq2 = sq2;
@@ -350,153 +576,16 @@ fn rook_slide_mask(sq: Square, occ: Bitboard) -> Bitboard {
     slide_mask(sq, occ, &[NORTH, SOUTH, EAST, WEST])
 }
 
-unsafe fn init_bishop_attacks(sq: Square,
-                              size: usize,
-                              occ: &[Bitboard; 4096],
-                              gold: &[Bitboard; 4096])
-                              -> bool {
-    ::std::intrinsics::write_bytes(&mut bishop_attacks_bb[sq.index()][0], 0, 512);
-    for i in 0..size {
-        let att: *mut Bitboard =
-            &mut bishop_attacks_bb[sq.index()][magic_bishop_index(sq, occ[i as usize])];
-        if *att != 0 && *att != gold[i as usize] {
-            return false;
-        }
-        *att = gold[i as usize]
-    }
-    true
-}
-
-unsafe fn init_rook_attacks(sq: Square,
-                            size: usize,
-                            occ: &[Bitboard; 4096],
-                            gold: &[Bitboard; 4096])
-                            -> bool {
-    ::std::intrinsics::write_bytes(&mut rook_attacks_bb[sq.index()][0], 0, 4096);
-    for i in 0..size {
-        let att: *mut Bitboard =
-            &mut rook_attacks_bb[sq.index()][magic_rook_index(sq, occ[i as usize])];
-        if *att != 0 && *att != gold[i as usize] {
-            return false;
-        }
-        *att = gold[i as usize]
-    }
-    true
-}
-
-pub fn optimize_rook_seed() {
-    init_simple_bitboards();
-    init_mundane_attacks();
-    let mut seed = 35000;
-    let mut best_time: u64;
-    unsafe {
-        best_time = init_magic_opt(PieceType::Rook, 8452, u64::max_value());
-    }
-    println!("starting optimization...");
-    loop {
-        unsafe {
-            let t = init_magic_opt(PieceType::Rook, seed, best_time);
-            if t < best_time {
-                best_time = t;
-                println!("\nnew best seed: {}, {}ms", seed, best_time / 1000 / 1000);
-            }
-        }
-        seed += 1;
-        if seed % 500 == 0 {
-            println!("{}", seed);
-        }
-    }
-}
-
-pub fn optimize_bishop_seed() {
-    init_simple_bitboards();
-    init_mundane_attacks();
-    let mut seed = 0;
-    let mut best_time: u64 = u64::max_value();
-    println!("starting optimization...");
-    loop {
-        unsafe {
-            let t = init_magic_opt(PieceType::Bishop, seed, best_time);
-            if t < best_time {
-                best_time = t;
-                println!("\nnew best seed: {}, {}ms", seed, best_time / 1000 / 1000);
-            }
-        }
-        seed += 1;
-        if seed % 500 == 0 {
-            println!("{}", seed);
-        }
-    }
-}
-
-fn init_magic() {
-    // We cheat on initialization time by choosing rng seeds that are known to
-    // find conforming magic numbers quickly. This doesn't matter much for real
-    // applications, but it makes the edit/compile/test cycle much faster--it's
-    // mostly a feature for my own convenience in development, so the fact that
-    // the benefits don't necessarily translate across systems doesn't matter.
-    // I tested Seed values up to 100k.
-    unsafe { init_magic_opt(PieceType::Bishop, 17337, u64::max_value()); }
-    unsafe { init_magic_opt(PieceType::Rook, 8452, u64::max_value()); }
-}
-
-unsafe fn init_magic_opt(pt: PieceType, xseed: usize, best_time: u64) -> u64{
-    let t1 = ::std::time::Instant::now();
-    let mut occ: [Bitboard; 4096] = [0; 4096];
-    let mut gold: [Bitboard; 4096] = [0; 4096];
-    let mut masks = if pt == PieceType::Bishop { &mut bishop_masks } else { &mut rook_masks };
-    let mut magic = if pt == PieceType::Bishop { &mut bishop_magic } else { &mut rook_magic };
-    let mask_fn = if pt == PieceType::Bishop { bishop_slide_mask } else { rook_slide_mask };
-    let attack_fn = if pt == PieceType::Bishop { init_bishop_attacks } else { init_rook_attacks };
-
-    use rand::{Rng, SeedableRng, StdRng};
-    let seed: &[_] = &[xseed];
-    let mut prng: StdRng = SeedableRng::from_seed(seed);
-    for sq in each_square() {
-        let rank_mask = (bb(Rank::_1) | bb(Rank::_8)) & !bb(sq.rank());
-        let file_mask = (bb(File::A) | bb(File::H)) & !bb(sq.file());
-        masks[sq.index()] = mask_fn(sq, 0) & !(rank_mask | file_mask);
-
-        // Each subset of masks[sq] is a possible occupancy mask that we must
-        // handle. Enumerate them and store both the occupancy and the reference
-        // attack set that we want to generate for that occupancy.
-        // See http://chessprogramming.wikispaces.com/Traversing+Subsets+of+a+Set
-        let (mut size, mut subset): (usize, Bitboard) = (0, 0);
-        while size == 0 || subset != 0 {
-            occ[size] = subset;
-            gold[size] = mask_fn(sq, subset);
-            subset = subset.wrapping_sub(masks[sq.index()]) & masks[sq.index()];
-            size += 1;
-        }
-
-        // Find a magic number that works by trial and error.
-        loop {
-            let elapsed_ms = in_millis(&t1.elapsed());
-            if elapsed_ms > best_time {
-                return u64::max_value();
-            }
-            magic[sq.index()] = prng.gen::<u64>() & prng.gen::<u64>() & prng.gen::<u64>();
-            if (magic[sq.index()].wrapping_mul(masks[sq.index()]) >> 56).count_ones() < 6 {
-                continue;
-            }
-            if attack_fn(sq, size, &occ, &gold) {
-                break;
-            }
-        }
-    }
-    in_millis(&t1.elapsed())
-}
-
-static mut bishop_pseudo_attacks_bb: [Bitboard; 64] = [0; 64];
-static mut rook_pseudo_attacks_bb: [Bitboard; 64] = [0; 64];
-static mut queen_pseudo_attacks_bb: [Bitboard; 64] = [0; 64];
+static mut bishop_pseudo_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
+static mut rook_pseudo_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
+static mut queen_pseudo_attacks_bb: [Bitboard; 64] = [Bitboard(0); 64];
 
 fn init_pseudo_attacks() {
     for sq in each_square() {
         unsafe {
-            bishop_pseudo_attacks_bb[sq.index()] = bishop_attacks(sq, 0);
-            rook_pseudo_attacks_bb[sq.index()] = rook_attacks(sq, 0);
-            queen_pseudo_attacks_bb[sq.index()] = queen_attacks(sq, 0);
+            bishop_pseudo_attacks_bb[sq.index()] = bishop_attacks(sq, Bitboard(0));
+            rook_pseudo_attacks_bb[sq.index()] = rook_attacks(sq, Bitboard(0));
+            queen_pseudo_attacks_bb[sq.index()] = queen_attacks(sq, Bitboard(0));
         }
     }
 }
@@ -513,16 +602,16 @@ pub fn queen_pseudo_attacks(sq: Square) -> Bitboard {
     unsafe { queen_pseudo_attacks_bb[sq.index()] }
 }
 
-static mut rays_bb: [[Bitboard; 64]; 64] = [[0; 64]; 64];
-static mut between_bb: [[Bitboard; 64]; 64] = [[0; 64]; 64];
+static mut rays_bb: [[Bitboard; 64]; 64] = [[Bitboard(0); 64]; 64];
+static mut between_bb: [[Bitboard; 64]; 64] = [[Bitboard(0); 64]; 64];
 
 fn init_post_attack_bitboards() {
     for sq1 in each_square() {
         for sq2 in each_square() {
-            if queen_pseudo_attacks(sq1) & bb(sq2) == 0 {
+            if !queen_pseudo_attacks(sq1).contains(sq2) {
                 continue;
             }
-            if bishop_pseudo_attacks(sq1) & bb(sq2) != 0 {
+            if bishop_pseudo_attacks(sq1).contains(sq2) {
                 unsafe {
                     rays_bb[sq2.index()][sq1.index()] =
                         bishop_pseudo_attacks(sq1) & bishop_pseudo_attacks(sq2) | bb(sq1) | bb(sq2);
@@ -553,6 +642,20 @@ pub fn ray(sq1: Square, sq2: Square) -> Bitboard {
     unsafe { rays_bb[sq1.index()][sq2.index()] }
 }
 
+// Alias for `ray` that makes call sites reaching for the whole line through
+// two squares (rather than a ray that happens to pass through them) easier
+// to read.
+pub fn line(sq1: Square, sq2: Square) -> Bitboard {
+    ray(sq1, sq2)
+}
+
+// True when sq3 lies on the infinite line through sq1 and sq2. Used for pin
+// handling and discovered-check detection, where a mover is only safe if it
+// stays aligned with the slider and the king it's pinned against.
+pub fn aligned(sq1: Square, sq2: Square, sq3: Square) -> bool {
+    line(sq1, sq2).contains(sq3)
+}
+
 pub fn king_attacks(sq: Square) -> Bitboard {
     unsafe { king_attacks_bb[sq.index()] }
 }
@@ -579,11 +682,23 @@ pub fn pawn_attacks(c: Color, sq: Square) -> Bitboard {
 }
 
 pub fn bishop_attacks(sq: Square, occ: Bitboard) -> Bitboard {
-    unsafe { bishop_attacks_bb[sq.index()][magic_bishop_index(sq, occ)] }
+    unsafe {
+        if use_pext {
+            Bitboard(BISHOP_PEXT_ATTACKS[pext_bishop_index(sq, occ)])
+        } else {
+            Bitboard(BISHOP_ATTACKS[magic_bishop_index(sq, occ)])
+        }
+    }
 }
 
 pub fn rook_attacks(sq: Square, occ: Bitboard) -> Bitboard {
-    unsafe { rook_attacks_bb[sq.index()][magic_rook_index(sq, occ)] }
+    unsafe {
+        if use_pext {
+            Bitboard(ROOK_PEXT_ATTACKS[pext_rook_index(sq, occ)])
+        } else {
+            Bitboard(ROOK_ATTACKS[magic_rook_index(sq, occ)])
+        }
+    }
 }
 
 pub fn queen_attacks(sq: Square, occ: Bitboard) -> Bitboard {
@@ -602,7 +717,7 @@ mod tests {
             assert_eq!(s, bb_to_str(x));
         };
 
-        test_case(0,
+        test_case(Bitboard(0),
                   "\n........\n........\n........\n........\n........\n........\n........\n........\n");
         test_case(bb!(E4, E5, D4, D5),
                   "\n........\n........\n........\n...xx...\n...xx...\n........\n........\n........\n");
@@ -610,18 +725,33 @@ mod tests {
                   "\nx.......\n.x......\n........\n........\n........\n........\n.x......\nx.......\n");
     });
 
+    chess_test!(test_bb_parse_and_display, {
+        assert_eq!(Bitboard::from_hex("0x8000000000000001"), Some(bb!(A1, H8)));
+        assert_eq!(Bitboard::from_hex("8000000000000001"), Some(bb!(A1, H8)));
+        assert_eq!(Bitboard::from_bits_str("0x8000000000000001"), Some(bb!(A1, H8)));
+        assert_eq!(Bitboard::from_bits_str("0b11"), Some(bb!(A1, B1)));
+        assert_eq!(Bitboard::from_bits_str("0o17"), Some(bb!(A1, B1, C1, D1)));
+        assert_eq!(Bitboard::from_bits_str("not hex"), None);
+
+        assert_eq!(format!("{}", bb!(A1, H8)),
+                   ".......X\n........\n........\n........\n........\n........\n........\nX.......\n");
+        assert_eq!(format!("{:x}", Bitboard(0xff)), "ff");
+        assert_eq!(format!("{:#x}", Bitboard(0x8000000000000001)),
+                   "0x8000_0000_0000_0001");
+    });
+
     chess_test!(test_pop_square, {
-        let test_case = |mut x, want, popped| {
+        let test_case = |mut x: Bitboard, want, popped| {
             assert_eq!(want, lsb(x));
             let got = pop_square(&mut x);
             assert_eq!(want, got);
             assert_eq!(popped, x);
         };
-        test_case(1, A1, 0);
-        test_case(2, B1, 0);
-        test_case(0xff, A1, 0xfe);
-        test_case(bb(H8), H8, 0);
-        test_case(bb(A1), A1, 0);
+        test_case(Bitboard(1), A1, Bitboard(0));
+        test_case(Bitboard(2), B1, Bitboard(0));
+        test_case(Bitboard(0xff), A1, Bitboard(0xfe));
+        test_case(bb(H8), H8, Bitboard(0));
+        test_case(bb(A1), A1, Bitboard(0));
         test_case(bb!(E4, E5, D4, D5), D4, bb!(E4, E5, D5));
     });
 
@@ -655,7 +785,42 @@ mod tests {
         assert_eq!(rook_attacks(F6, bb!(B3, C2, C3, C6, D5, D7, F6)),
                    bb!(C6, D6, E6, G6, H6, F1, F2, F3, F4, F5, F7, F8));
     });
-    
+
+    // The magic/PEXT tables are generated at build time (see build.rs), so
+    // this isn't checking the search itself -- it's checking that the
+    // baked-in tables of whichever backend (PEXT or fancy-magic) this CPU
+    // selects agree with `{rook,bishop}_slide_mask`, the slow ray-walk those
+    // tables short-circuit; the other backend's tables aren't exercised on
+    // the same machine. Exhaustive over
+    // every subset of each square's relevant-occupancy mask via the
+    // carry-rippler trick, rather than a handful of samples, since it's
+    // cheap enough to just check them all.
+    chess_test!(test_slider_attacks_vs_reference, {
+        for sq in each_square() {
+            let mask = ROOK_MASKS[sq.index()];
+            let mut occ = 0u64;
+            loop {
+                let b = Bitboard(occ);
+                assert_eq!(rook_attacks(sq, b), rook_slide_mask(sq, b));
+                occ = occ.wrapping_sub(mask) & mask;
+                if occ == 0 {
+                    break;
+                }
+            }
+
+            let mask = BISHOP_MASKS[sq.index()];
+            let mut occ = 0u64;
+            loop {
+                let b = Bitboard(occ);
+                assert_eq!(bishop_attacks(sq, b), bishop_slide_mask(sq, b));
+                occ = occ.wrapping_sub(mask) & mask;
+                if occ == 0 {
+                    break;
+                }
+            }
+        }
+    });
+
     chess_test!(test_queen_attacks, {
         assert_eq!(queen_attacks(A1, bb_from_str("x......x\n........\nxxx.....\n........\n........\n.x....x.\n........\nx......x\n")),
                    bb_from_str(".......x\n......x.\nx....x..\nx...x...\nx..x....\nx.x.....\nxx......\n.xxxxxxx\n"));
@@ -672,10 +837,19 @@ mod tests {
         assert_eq!(between(G5, D2), bb!(E3, F4));
         assert_eq!(ray(D2, G5), bb!(C1, D2, E3, F4, G5, H6));
         assert_eq!(ray(G5, D2), bb!(C1, D2, E3, F4, G5, H6));
-        assert_eq!(between(A1, B3), 0);
-        assert_eq!(between(B3, A1), 0);
-        assert_eq!(ray(A1, B3), 0);
-        assert_eq!(ray(B3, A1), 0);
+        assert_eq!(between(A1, B3), Bitboard(0));
+        assert_eq!(between(B3, A1), Bitboard(0));
+        assert_eq!(ray(A1, B3), Bitboard(0));
+        assert_eq!(ray(B3, A1), Bitboard(0));
+    });
+
+    chess_test!(test_aligned, {
+        assert_eq!(line(C3, E3), ray(C3, E3));
+        assert!(aligned(C3, D3, E3));
+        assert!(aligned(C3, E3, A3));
+        assert!(aligned(D2, F4, H6));
+        assert!(!aligned(D2, F4, H7));
+        assert!(!aligned(A1, B3, C4));
     });
 
     chess_test!(test_pawn_masks, {
@@ -702,7 +876,7 @@ mod tests {
                                                        E2, G2,
                                                        E1, G1));
     });
-    
+
     chess_test!(test_color_masks, {
         let wsq = squares_of_color(E4);
         let bsq = squares_of_color(A1);
@@ -714,6 +888,16 @@ mod tests {
                             F2, F4, F6, F8,
                             G1, G3, G5, G7,
                             H2, H4, H6, H8));
-        assert_eq!(wsq, u64::max_value() ^ bsq);
+        assert_eq!(wsq, Bitboard(u64::max_value()) ^ bsq);
+    });
+
+    chess_test!(test_distance_ring, {
+        assert_eq!(distance_ring(A1, 0), bb(A1));
+        assert_eq!(distance_ring(A1, 1), bb!(A2, B1, B2));
+        assert_eq!(distance_ring(D4, 1), bb!(C3, D3, E3, C4, E4, C5, D5, E5));
+        for d in 0..8u8 {
+            assert_eq!(distance_ring(E4, d).count(),
+                       each_square().filter(|&sq| dist(E4, sq) == d).count() as u32);
+        }
     });
 }
@@ -0,0 +1,46 @@
+// Loads persistent engine settings from a `daydreamer.toml` config file, so
+// a user can keep a stable local tuning profile instead of re-typing
+// `setoption`/CLI flags every session -- the same idea as rustc's
+// bootstrap `config.toml` or rmenu's config module. Applied in `main`
+// before anything else starts, with precedence compiled defaults < config
+// file < command-line flags: this module only overrides what the file
+// actually sets, and `main` applies any CLI flags afterward.
+use std::fs;
+use std::path::Path;
+
+use options;
+
+pub const DEFAULT_CONFIG_FILE: &'static str = "daydreamer.toml";
+
+// Parses `path` as TOML and applies whatever settings it recognizes via
+// `options::set_*`. A missing file is not an error -- it just means "use
+// compiled-in defaults" -- but a present, unparseable file panics, since
+// that's a typo worth surfacing loudly rather than silently ignoring.
+pub fn load(path: &str) {
+    if !Path::new(path).exists() { return }
+    let contents = fs::read_to_string(path).expect("failed to read config file");
+    let value: toml::Value = contents.parse().expect("failed to parse config file as TOML");
+    let table = match value.as_table() {
+        Some(t) => t,
+        None => return,
+    };
+
+    if let Some(v) = table.get("hash").and_then(|v| v.as_integer()) {
+        options::set_hash(v as usize);
+    }
+    if let Some(v) = table.get("threads").and_then(|v| v.as_integer()) {
+        options::set_threads(v as usize);
+    }
+    if let Some(v) = table.get("multipv").and_then(|v| v.as_integer()) {
+        options::set_multi_pv(v as usize);
+    }
+    if let Some(v) = table.get("contempt").and_then(|v| v.as_integer()) {
+        options::set_contempt(v as i32);
+    }
+    if let Some(v) = table.get("skill_level").and_then(|v| v.as_integer()) {
+        options::set_skill_level(v as u32);
+    }
+    if let Some(v) = table.get("own_book").and_then(|v| v.as_bool()) {
+        options::set_own_book(v);
+    }
+}
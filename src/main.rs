@@ -1,34 +1,134 @@
 #![allow(dead_code)]
 
-extern crate rand;
 #[macro_use] extern crate lazy_static;
+extern crate clap;
+extern crate image;
+extern crate toml;
 
 #[macro_use] pub mod macros;
+pub mod bench;
 pub mod board;
 pub mod bitboard;
+pub mod book;
+pub mod config;
 pub mod eval;
 pub mod movement;
 pub mod movegen;
 pub mod options;
 pub mod perft;
 pub mod position;
+pub mod render;
 pub mod score;
 pub mod search;
+pub mod tablebase;
 pub mod transposition;
 pub mod uci;
 
+use clap::{Parser, Subcommand};
+
+// Command-line surface for the engine. With no subcommand this behaves like
+// the old entry point (read UCI commands from stdin), but `--hash`/
+// `--threads`/`--multipv` let a GUI or script configure the engine without
+// speaking UCI for it first, and `--commands-file` replaces the old
+// "every bare argument is a commands file" convention with an explicit,
+// repeatable flag.
+#[derive(Parser)]
+#[command(name = "daydreamer", version = env!("CARGO_PKG_VERSION"), author = "Aaron Becker")]
+struct Cli {
+    /// Transposition table size, in MB.
+    #[arg(long, env = "DAYDREAMER_HASH")]
+    hash: Option<usize>,
+
+    /// Number of Lazy SMP search threads.
+    #[arg(long, env = "DAYDREAMER_THREADS")]
+    threads: Option<usize>,
+
+    /// Number of principal variations to search and report.
+    #[arg(long = "multipv", env = "DAYDREAMER_MULTIPV")]
+    multi_pv: Option<usize>,
+
+    /// Config file to load persistent settings from. Defaults to
+    /// `daydreamer.toml` in the working directory if present.
+    #[arg(long)]
+    config: Option<String>,
+
+    /// A file of UCI commands to run before reading from stdin. May be
+    /// given more than once; files run in the order given.
+    #[arg(long = "commands-file")]
+    commands_file: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Read UCI commands from stdin (the default with no subcommand).
+    Uci,
+    /// Run the fixed benchmark suite and report a node-count signature.
+    Bench {
+        /// Overrides the fixed search depth used for every position.
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Overrides the built-in position set with one FEN per line from
+        /// this file.
+        #[arg(long)]
+        positions: Option<String>,
+    },
+    /// Count (and divide) leaf nodes from the current position to `depth`.
+    Perft {
+        depth: u32,
+    },
+}
+
 fn main() {
     println!("Daydreamer {} ({}), by Aaron Becker",
              env!("CARGO_PKG_VERSION"),
              include_str!(concat!(env!("OUT_DIR"), "/version.rs")));
     bitboard::initialize();
     position::initialize();
-    ::options::set_multi_pv(1);
+
+    let cli = Cli::parse();
+
+    // Three-level precedence: compiled-in defaults, then a config file,
+    // then command-line flags, each overriding the last.
+    let config_path = cli.config.clone().unwrap_or_else(|| config::DEFAULT_CONFIG_FILE.to_string());
+    config::load(&config_path);
+    if let Some(mb) = cli.hash {
+        ::options::set_hash(mb);
+    }
+    if let Some(threads) = cli.threads {
+        ::options::set_threads(threads);
+    }
+    if let Some(multi_pv) = cli.multi_pv {
+        ::options::set_multi_pv(multi_pv);
+    }
+
     let mut search_data = search::SearchData::new();
 
-    // Treat each argument as a file containing uci commands.
-    for arg in ::std::env::args().skip(1) {
-        uci::read_stream(&mut search_data, Some(arg.to_string()));
+    match cli.command.unwrap_or(Command::Uci) {
+        Command::Bench { depth, positions } => {
+            let position_set = match positions {
+                Some(path) => ::std::fs::read_to_string(path)
+                    .expect("failed to read --positions file")
+                    .lines()
+                    .map(|line| line.to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect(),
+                None => bench::DEFAULT_POSITIONS.iter().map(|s| s.to_string()).collect(),
+            };
+            bench::run(&position_set, depth.unwrap_or(bench::DEFAULT_DEPTH));
+            return;
+        }
+        Command::Perft { depth } => {
+            perft::divide(&mut search_data.pos, depth);
+            return;
+        }
+        Command::Uci => {}
+    }
+
+    for path in cli.commands_file.iter() {
+        uci::read_stream(&mut search_data, Some(path.to_string()));
     }
     // Read from stdin.
     uci::read_stream(&mut search_data, None);
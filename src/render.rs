@@ -0,0 +1,38 @@
+// Turns a `Bitboard` into a PNG so a set of squares can be eyeballed instead
+// of decoded from hex. Handy for sanity-checking mask generation (e.g.
+// `squares_of_color`, the passer/outpost masks, or a freshly generated
+// attack set) during development.
+use std::path::Path;
+
+use image::{ImageResult, Rgba, RgbaImage};
+
+use bitboard::{self, Bitboard};
+use board::*;
+
+const LIGHT_SQUARE: Rgba<u8> = Rgba { data: [240, 217, 181, 255] };
+const DARK_SQUARE: Rgba<u8> = Rgba { data: [181, 136, 99, 255] };
+
+// Renders `b` as an 8x8 checkerboard with every set bit painted `highlight`,
+// `square_px` pixels to a side. Orientation matches the square constants:
+// A1 is the bottom-left square, H8 the top-right, as on a physical board
+// viewed from White's side.
+pub fn render_bitboard(b: Bitboard, highlight: Rgba<u8>, square_px: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(square_px * 8, square_px * 8);
+    let dark_squares = bitboard::squares_of_color(Square::A1);
+    for sq in each_square() {
+        let base = if dark_squares.contains(sq) { DARK_SQUARE } else { LIGHT_SQUARE };
+        let color = if b.contains(sq) { highlight } else { base };
+        let col = sq.file().index() as u32;
+        let row = 7 - sq.rank().index() as u32;
+        for dy in 0..square_px {
+            for dx in 0..square_px {
+                img.put_pixel(col * square_px + dx, row * square_px + dy, color);
+            }
+        }
+    }
+    img
+}
+
+pub fn save_png<P: AsRef<Path>>(img: &RgbaImage, path: P) -> ImageResult<()> {
+    img.save(path)
+}
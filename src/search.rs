@@ -5,6 +5,7 @@ use std::time::{Duration, Instant};
 
 use bitboard;
 use board;
+use book;
 use board::{Rank, PieceType};
 use eval;
 use movegen::MoveSelector;
@@ -14,6 +15,7 @@ use position;
 use position::{AttackData, Position, UndoState};
 use score;
 use score::{Score, score_is_valid, is_mate_score};
+use tablebase;
 use transposition;
 use uci::in_millis;
 
@@ -27,13 +29,120 @@ const RAZOR_DEPTH: SearchDepth = 3.5;
 const RAZOR_MARGIN: [Score; 4] = [0 /* unused */, 300, 300, 325];
 
 const IID_ENABLED: bool = true;
+// Minimum depth before a missing tt_move triggers internal iterative
+// deepening, and how close `lazy_score` has to be to `beta` for it to be
+// worth the extra reduced-depth search -- PV nodes get a lower depth bar
+// since move ordering matters more there and the search is already cheaper
+// per node than a null-window re-search.
+const IID_PV_MIN_DEPTH: SearchDepth = 5.;
+const IID_PV_MARGIN: Score = 300;
+const IID_NON_PV_MIN_DEPTH: SearchDepth = 8.;
+const IID_NON_PV_MARGIN: Score = 150;
+
+// Minimum depth, and how far the tt entry's own depth may trail the current
+// depth, before a tt_move is considered for singular-extension verification
+// -- shallow entries aren't trustworthy enough to justify the extra
+// verification search.
+const SINGULAR_MIN_DEPTH: SearchDepth = 8.;
+const SINGULAR_TT_DEPTH_SLACK: SearchDepth = 3.;
+
+// How far below the tt score the verification search's null window sits.
+// Grows with depth, so at higher depths the window sits further below the tt
+// score and an alternative needs to come less close to it to show the
+// tt_move isn't singular.
+fn singular_margin(d: SearchDepth) -> Score {
+    (2. * d) as Score
+}
 
-fn futility_margin(d: SearchDepth) -> Score {
-    if is_quiescence_depth(d) {
-        65. as Score
+// UCI `Skill Level` tops out at 20, meaning full strength / no throttling.
+const MAX_SKILL_LEVEL: u32 = 20;
+
+// Nodes between throttling naps for each `Skill Level` 0..=20, growing
+// geometrically so skill 0 is slowed to a crawl while skill 19 is barely
+// held back at all. Skill 20 (or `UCI_LimitStrength` off) is full strength
+// and isn't throttled.
+const SKILL_NODE_PERIOD: [u64; 21] = [
+    19, 41, 70, 110, 160, 230, 320, 450, 630, 880,
+    1230, 1730, 2420, 3390, 4750, 6650, 9310, 13030, 18240, 25540, 0,
+];
+
+// How many of the best root moves a weakened skill level is allowed to pick
+// among, rather than always playing `root_moves[0]`.
+const SKILL_MULTI_PV: usize = 4;
+
+// The `MultiPV` to actually widen the search to: the user's configured
+// value, unless `Skill Level` is below max, in which case it's widened to
+// `SKILL_MULTI_PV` so `skill_adjusted_move` has several ranked, fully
+// searched candidates to weaken between.
+fn effective_multi_pv() -> usize {
+    let configured = options::multi_pv();
+    if options::skill_level() < MAX_SKILL_LEVEL {
+        max!(configured, SKILL_MULTI_PV)
     } else {
-        (85. + 15. * d + 2. * d * d) as Score
+        configured
+    }
+}
+
+// Sleeps briefly every `SKILL_NODE_PERIOD[skill]` nodes so the engine's
+// effective node rate -- and so its playing strength -- drops as `Skill
+// Level` falls below max. A no-op once skill is maxed or `UCI_LimitStrength`
+// has it clamped there.
+fn apply_skill_slowdown(data: &SearchData) {
+    let skill = min!(options::skill_level(), MAX_SKILL_LEVEL);
+    let period = SKILL_NODE_PERIOD[skill as usize];
+    if period == 0 || data.stats.nodes % period != 0 { return }
+    thread::sleep(Duration::from_millis(1));
+}
+
+// A tiny xorshift64 step, seeded from the position and a per-candidate
+// counter, used to weaken move choice below. Not cryptographic -- just
+// needs to vary from move to move and game to game without pulling in a
+// dependency for it.
+fn xorshift64(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+// Below max skill, picks among the top `SKILL_MULTI_PV` root moves
+// instead of always the very best: each candidate's score is penalized by
+// pseudo-random noise whose spread grows as skill drops, so a weak skill
+// level sometimes settles for a slightly inferior but still plausible move.
+// At max skill (or once there's only one root move to choose from) this is
+// just `root_moves[0]`.
+fn skill_adjusted_move(data: &SearchData) -> Move {
+    let skill = min!(options::skill_level(), MAX_SKILL_LEVEL);
+    if skill >= MAX_SKILL_LEVEL || data.root_moves.len() < 2 {
+        return data.root_moves[0].m;
+    }
+
+    let candidates = min!(SKILL_MULTI_PV, data.root_moves.len());
+    let spread = ((MAX_SKILL_LEVEL - skill) as Score) * 32;
+    let mut seed = data.pos.hash() ^ (data.stats.nodes.wrapping_add(1));
+    let (mut best_idx, mut best_score) = (0, score::MIN_SCORE);
+    for i in 0..candidates {
+        seed = xorshift64(seed.wrapping_add(i as u64));
+        let noise = (seed % (spread.max(1) as u64)) as Score;
+        let weakened = data.root_moves[i].score - noise;
+        if weakened > best_score {
+            best_score = weakened;
+            best_idx = i;
+        }
     }
+    data.root_moves[best_idx].m
+}
+
+// Smaller when the side to move isn't improving (its static eval hasn't
+// risen since its last move two plies ago), so futility pruning cuts more
+// aggressively in positions that are trending the wrong way.
+fn futility_margin(d: SearchDepth, improving: bool) -> Score {
+    let margin = if is_quiescence_depth(d) {
+        65.
+    } else {
+        85. + 15. * d + 2. * d * d
+    };
+    if improving { margin as Score } else { (margin * 0.7) as Score }
 }
 
 // Inside the search, we keep the remaining depth to search as a floating point
@@ -111,7 +220,9 @@ pub enum SearchResult {
 
 // SearchConstraints track the conditions for a search as specified via UCI.
 // This is mostly about how much searching we should do before stopping, but
-// also includes a list of moves to consider at the root.
+// also includes a list of moves to consider at the root. Cloned into each
+// Lazy SMP helper thread so every thread agrees on what it's searching for.
+#[derive(Clone)]
 pub struct SearchConstraints {
     pub infinite: bool,
     pub ponder : bool,
@@ -196,6 +307,7 @@ pub struct SearchStats {
     nodes: u64,
     qnodes: u64,
     pvnodes: u64,
+    tb_hits: u64,
 }
 
 impl SearchStats {
@@ -204,10 +316,12 @@ impl SearchStats {
             nodes: 0,
             qnodes: 0,
             pvnodes: 0,
+            tb_hits: 0,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct RootMove {
     pub m: Move,
     pub score: Score,
@@ -227,15 +341,64 @@ impl RootMove {
 }
 
 
+// Tracks whether the root move has settled down across iterations so
+// `should_deepen` can stop early on an obvious recapture or forced reply
+// instead of burning the rest of the soft limit on a move that was never
+// going to change. `key` is the position two plies into the current PV --
+// not consumed yet, but cheap to keep around for a future ponderhit check.
+#[derive(Copy, Clone)]
+pub struct EasyMoveManager {
+    pv: [Move; 3],
+    key: u64,
+    stable_iterations: u32,
+}
+
+const EASY_MOVE_STABLE_ITERATIONS: u32 = 3;
+const EASY_MOVE_SCORE_MARGIN: Score = 100;
+// Trigger the early return once we're this far through the soft limit,
+// rather than waiting for the ordinary soft-limit check to fire.
+const EASY_MOVE_TIME_FRACTION: (u32, u32) = (6, 10);
+
+impl EasyMoveManager {
+    pub fn new() -> EasyMoveManager {
+        EasyMoveManager {
+            pv: [NO_MOVE; 3],
+            key: 0,
+            stable_iterations: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = EasyMoveManager::new();
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Node {
     pub killers: [Move; 2],
+    // The move played at this ply, if any. Read back from one and two
+    // plies up by `combined_history`/`record_success`/`record_failure` so
+    // continuation history can be keyed by "what was played just before
+    // this move", not just the move itself.
+    pub played: Move,
+    // Set for the duration of this node's singular-extension verification
+    // search, so a re-entrant call against the same node (see `search`)
+    // doesn't try to verify singularity again on top of itself.
+    pub singular_search: bool,
+    // This node's static eval (`lazy_score` in `search`, `static_eval` in
+    // `quiesce`), kept around so the node two plies up the stack -- the
+    // last time this side was on move -- can tell whether the position is
+    // improving. `score::MIN_SCORE` means "not computed yet".
+    pub static_eval: Score,
 }
 
 impl Node {
     pub fn new() -> Node {
         Node {
             killers: [NO_MOVE, NO_MOVE],
+            played: NO_MOVE,
+            singular_search: false,
+            static_eval: score::MIN_SCORE,
         }
     }
 }
@@ -251,13 +414,59 @@ pub struct SearchData {
     pub pv_stack: [[Move; MAX_PLY + 1]; MAX_PLY + 1],
     pub search_stack: [Node; MAX_PLY + 1],
     pub history: [Score; 64 * 16],
+    // Continuation ("counter-move") history: how well a (piece, to) move
+    // has historically followed a given (prev_piece, prev_to) move, one or
+    // two plies back. Flattened to a `Vec` rather than a
+    // `[[Score; 1024]; 1024]` array so it doesn't blow up the size of
+    // every `SearchData` (including the ones `clone_for_helper` creates
+    // per Lazy SMP thread); indexed via `cont_history_index`.
+    pub cont_history: Vec<Score>,
     pub countermoves: [[Move; 64]; 16],
-    pub tt: transposition::Table,
+    // Shared across every Lazy SMP thread searching this position, so a
+    // cutoff found by one thread is immediately visible to the others.
+    // `transposition::Table` is lock-free -- every slot is a few atomic
+    // words, self-checked against torn concurrent writes -- so `get`/`put`
+    // only need `&self` and sharing it as a plain `Arc` is sound -- no
+    // external synchronization required here.
+    pub tt: Arc<transposition::Table>,
+    // False for every thread but the one that owns `go()`'s UCI output.
+    // Helper threads run the exact same `deepening_search`/`search` code
+    // but must never print `info`/`bestmove` lines of their own.
+    pub is_main: bool,
+    // 0 for the main thread; 1..N for Lazy SMP helpers. Helpers use this to
+    // skip certain iterative-deepening depths (see `skip_iteration`) so that
+    // N threads chew on different depths instead of racing through an
+    // identical move order against an identical tt/history state.
+    pub helper_id: usize,
+    pub easy_move: EasyMoveManager,
+    // An optimism bonus derived from the previous iteration's root score,
+    // applied to `lazy_score` at every node so a sharp position keeps
+    // trusting the trend it already found rather than re-litigating it
+    // from a neutral eval baseline every iteration. Root-side-relative:
+    // `search`/`quiesce` flip its sign by ply parity to get the bonus for
+    // whichever side is actually on move at that node.
+    pub optimism: Score,
 }
 
 pub const MAX_HISTORY: Score = 10000;
 pub const MIN_HISTORY: Score = -10000;
 pub const EMPTY_HISTORY: [Score; 64 * 16] = [0; 64 * 16];
+pub const CONT_HISTORY_DIM: usize = 64 * 16;
+
+fn cont_history_index(prev_idx: usize, idx: usize) -> usize {
+    prev_idx * CONT_HISTORY_DIM + idx
+}
+
+// `bonus`/`penalty` follow the usual shape for history updates: quadratic in
+// depth so deeper cutoffs move the score by more, with a small constant
+// term so even depth-0 cutoffs nudge it.
+fn bonus(d: SearchDepth) -> Score {
+    (d * d + 2. * d - 2.) as Score
+}
+
+fn penalty(d: SearchDepth) -> Score {
+    -((d * d + 4. * d + 1.) as Score)
+}
 
 impl SearchData {
     pub fn new() -> SearchData {
@@ -274,8 +483,40 @@ impl SearchData {
             pv_stack: [[NO_MOVE; MAX_PLY + 1]; MAX_PLY + 1],
             search_stack: [Node::new(); MAX_PLY + 1],
             history: [0; 64 * 16],
+            cont_history: vec![0; CONT_HISTORY_DIM * CONT_HISTORY_DIM],
             countermoves: [[NO_MOVE; 64]; 16],
-            tt: transposition::Table::new(64 << 20),
+            tt: Arc::new(transposition::Table::new(64 << 20)),
+            is_main: true,
+            helper_id: 0,
+            easy_move: EasyMoveManager::new(),
+            optimism: 0,
+        }
+    }
+
+    // Builds an independent SearchData for a Lazy SMP helper thread: its own
+    // position, move-ordering state, and search stack, but the same shared
+    // transposition table and engine-state handle as the caller, so a single
+    // `go()` call starts and stops every thread together.
+    pub fn clone_for_helper(&self, id: usize) -> SearchData {
+        let (_, rx) = mpsc::channel();
+        SearchData {
+            pos: self.pos.clone(),
+            root_moves: self.root_moves.iter().map(|rm| RootMove::new(rm.m)).collect(),
+            current_depth: 0,
+            constraints: self.constraints.clone(),
+            stats: SearchStats::new(),
+            state: self.state.clone(),
+            uci_channel: rx,
+            pv_stack: [[NO_MOVE; MAX_PLY + 1]; MAX_PLY + 1],
+            search_stack: [Node::new(); MAX_PLY + 1],
+            history: EMPTY_HISTORY,
+            cont_history: vec![0; CONT_HISTORY_DIM * CONT_HISTORY_DIM],
+            countermoves: [[NO_MOVE; 64]; 16],
+            tt: Arc::clone(&self.tt),
+            is_main: false,
+            helper_id: id,
+            easy_move: EasyMoveManager::new(),
+            optimism: 0,
         }
     }
 
@@ -284,6 +525,8 @@ impl SearchData {
         self.current_depth = 0;
         self.stats = SearchStats::new();
         self.pv_stack = [[NO_MOVE; MAX_PLY + 1]; MAX_PLY + 1];
+        self.easy_move.clear();
+        self.optimism = 0;
     }
 
     pub fn should_stop(&self) -> bool {
@@ -292,6 +535,7 @@ impl SearchData {
         if engine_state == STOPPING_STATE { return true }
         if self.stats.nodes >= self.constraints.node_limit &&
            !self.constraints.infinite { return true }
+        apply_skill_slowdown(self);
         false
     }
 
@@ -299,28 +543,80 @@ impl SearchData {
         m.piece().index() << 6 | m.to().index()
     }
 
-    pub fn record_success(&mut self, m: Move, d: SearchDepth) {
+    // Bumps both the plain history table and, for whichever of the one- and
+    // two-ply-back moves actually exist (tracked via `search_stack[..].played`),
+    // the continuation history keyed off of them.
+    pub fn record_success(&mut self, ply: usize, m: Move, d: SearchDepth) {
         let index = SearchData::history_index(m);
-        self.history[index] += (d * d) as Score;
+        let b = bonus(d);
+        self.history[index] += b;
         if self.history[index] > MAX_HISTORY {
             for i in 0..(64 * 16) {
                 self.history[i] = self.history[i] >> 1;
             }
         }
+        self.bump_cont_history(ply, index, b);
+
         let lm = self.pos.last_move();
         if lm != NO_MOVE && lm != NULL_MOVE {
             self.countermoves[lm.piece().index()][lm.to().index()] = m;
         }
     }
 
-    pub fn record_failure(&mut self, m: Move, d: SearchDepth) {
+    pub fn record_failure(&mut self, ply: usize, m: Move, d: SearchDepth) {
         let index = SearchData::history_index(m);
-        self.history[index] -= (d * d) as Score;
+        let p = penalty(d);
+        self.history[index] += p;
         if self.history[index] < MIN_HISTORY {
             for i in 0..(64 * 16) {
                 self.history[i] = self.history[i] >> 1;
             }
         }
+        self.bump_cont_history(ply, index, p);
+    }
+
+    // The countermove on file for whatever move was just played, i.e. the
+    // quiet reply that most recently caused a beta cutoff against it, or
+    // `NO_MOVE` if the position has no last move (root) or none has been
+    // recorded yet.
+    pub fn countermove(&self) -> Move {
+        let lm = self.pos.last_move();
+        if lm == NO_MOVE || lm == NULL_MOVE {
+            return NO_MOVE;
+        }
+        self.countermoves[lm.piece().index()][lm.to().index()]
+    }
+
+    fn bump_cont_history(&mut self, ply: usize, index: usize, delta: Score) {
+        for back in 1..3 {
+            if ply < back { continue }
+            let prev = self.search_stack[ply - back].played;
+            if prev == NO_MOVE || prev == NULL_MOVE { continue }
+            let prev_idx = SearchData::history_index(prev);
+            let i = cont_history_index(prev_idx, index);
+            self.cont_history[i] += delta;
+            if self.cont_history[i] > MAX_HISTORY || self.cont_history[i] < MIN_HISTORY {
+                let row = prev_idx * CONT_HISTORY_DIM;
+                for j in row..row + CONT_HISTORY_DIM {
+                    self.cont_history[j] = self.cont_history[j] >> 1;
+                }
+            }
+        }
+    }
+
+    // The score `MoveSelector` and `reduction` should use to order/weight
+    // `m` at this ply: plain history plus whatever continuation history
+    // has on file for it following the one- and two-ply-back moves.
+    pub fn combined_history(&self, ply: usize, m: Move) -> Score {
+        let index = SearchData::history_index(m);
+        let mut score = self.history[index];
+        for back in 1..3 {
+            if ply < back { continue }
+            let prev = self.search_stack[ply - back].played;
+            if prev == NO_MOVE || prev == NULL_MOVE { continue }
+            score += self.cont_history[cont_history_index(SearchData::history_index(prev), index)];
+        }
+        score
     }
 
     pub fn clear_pv(&mut self, ply: usize) {
@@ -339,6 +635,32 @@ impl SearchData {
     }
 }
 
+// The non-interactive counterpart to `go()` that `bench` uses: searches
+// `data.pos` to a fixed `depth` with no timer, no pondering, and no Lazy
+// SMP helpers, so the resulting node count depends on nothing but the
+// position, the depth, and the engine binary -- the determinism `bench`
+// needs for its node-count signature to be diffable across runs. Callers
+// are expected to hand in a `SearchData` with a fresh transposition table.
+pub fn bench_to_depth(data: &mut SearchData, depth: Depth) {
+    data.constraints = SearchConstraints::new();
+    data.constraints.depth_limit = depth;
+    data.state.enter(SEARCHING_STATE);
+    data.reset();
+
+    let ad = AttackData::new(&data.pos);
+    let mut ms = MoveSelector::legal();
+    while let Some(m) = ms.next(&data.pos, &ad, &data.history) {
+        data.constraints.searchmoves.push(m);
+    }
+    for m in data.constraints.searchmoves.iter() {
+        data.root_moves.push(RootMove::new(*m));
+    }
+    data.tt.new_generation();
+
+    deepening_search(data);
+    data.state.enter(WAITING_STATE);
+}
+
 pub fn go(data: &mut SearchData) {
     // Spawn a thread that will wake up when we hit our time limit and change
     // our state to STOPPING if the search hasn't terminated yet. This lets
@@ -376,12 +698,20 @@ pub fn go(data: &mut SearchData) {
         println!("bestmove (none)");
         return
     }
+    if let Some(m) = book_move(data) {
+        data.state.enter(WAITING_STATE);
+        println!("bestmove {}", m);
+        return
+    }
     for m in data.constraints.searchmoves.iter() {
         data.root_moves.push(RootMove::new(*m));
     }
+    filter_root_moves_by_tablebase(data);
     data.tt.new_generation();
- 
+
+    let helpers = spawn_helpers(data);
     deepening_search(data);
+    let helper_results = join_helpers(helpers);
 
     loop {
         let engine_state = data.state.load();
@@ -402,13 +732,141 @@ pub fn go(data: &mut SearchData) {
                  in_millis(&data.constraints.soft_limit),
                  in_millis(&data.constraints.hard_limit));
     }
-    print!("bestmove {}", data.root_moves[0].m);
-    if data.root_moves[0].pv.len() > 0 {
-        print!(" ponder {}", data.root_moves[0].pv[0]);
+    if data.stats.tb_hits > 0 {
+        println!("info string tbhits {}", data.stats.tb_hits);
+    }
+    let best_data = best_thread(data, &helper_results);
+    let best_move = skill_adjusted_move(best_data);
+    let best_rm = best_data.root_moves.iter().find(|rm| rm.m == best_move).unwrap_or(&best_data.root_moves[0]);
+    print!("bestmove {}", best_move);
+    if best_rm.pv.len() > 0 {
+        print!(" ponder {}", best_rm.pv[0]);
     }
     println!("");
 }
 
+// Spawns `options::threads() - 1` Lazy SMP helpers, each running the same
+// `deepening_search` against its own copy of the position but sharing this
+// search's transposition table and stop signal. Returns immediately; the
+// helpers are joined back in (handing back each one's finished `SearchData`)
+// once the main thread's search has stopped.
+fn spawn_helpers(data: &SearchData) -> Vec<thread::JoinHandle<SearchData>> {
+    (1..options::threads())
+        .map(|id| {
+            let mut helper = data.clone_for_helper(id);
+            thread::spawn(move || { deepening_search(&mut helper); helper })
+        })
+        .collect()
+}
+
+// Joins every helper thread and collects its final `SearchData` -- dropping
+// any helper whose thread panicked, which leaves it out of `best_thread`'s
+// comparison entirely rather than crashing the main search over it.
+fn join_helpers(helpers: Vec<thread::JoinHandle<SearchData>>) -> Vec<SearchData> {
+    helpers.into_iter().filter_map(|helper| helper.join().ok()).collect()
+}
+
+// The last iterative-deepening depth `data` actually finished searching.
+// `current_depth` is bumped either after an iteration's aspiration window
+// converges or when a helper skips that depth, so whenever a thread stops
+// mid-iteration (the common case -- every thread shares the same stop
+// signal) `current_depth` itself still names the depth that was
+// interrupted, not one it completed; skipped depths below it are passed over.
+fn completed_depth(data: &SearchData) -> Depth {
+    let mut d = data.current_depth.saturating_sub(1);
+    while d > 0 && skip_iteration(data.helper_id, d) {
+        d -= 1;
+    }
+    d
+}
+
+// Picks whichever thread's search is most trustworthy to answer from: the
+// one that completed the greatest iterative-deepening depth, breaking ties
+// by its best root move's score. Lazy SMP helpers search the same root
+// position the main thread does (just skipping different depths, see
+// `skip_iteration`), so every candidate's `root_moves[0].score` is directly
+// comparable.
+fn best_thread<'a>(main: &'a SearchData, helpers: &'a [SearchData]) -> &'a SearchData {
+    let mut best = main;
+    for helper in helpers {
+        let better_depth = completed_depth(helper) > completed_depth(best);
+        let tied_depth_better_score = completed_depth(helper) == completed_depth(best) &&
+            helper.root_moves[0].score > best.root_moves[0].score;
+        if better_depth || tied_depth_better_score {
+            best = helper;
+        }
+    }
+    best
+}
+
+// Probes WDL for `data.pos` and, when `UseRule50` is on, folds a cursed win
+// or blessed loss back to a plain draw once the position's fifty-move
+// counter is far enough along that the rule could plausibly save the game
+// before the win could be converted. Below that point (or with `UseRule50`
+// off) the sharper result is reported as-is.
+fn probe_wdl_with_rule50(data: &SearchData) -> Option<tablebase::Wdl> {
+    let wdl = tablebase::probe_wdl(&data.pos)?;
+    Some(tablebase::adjust_for_rule50(wdl, data.pos.halfmove_clock(), options::use_rule50()))
+}
+
+// Probes WDL for every candidate root move and narrows `searchmoves`/
+// `root_moves` down to only those that preserve the best achievable result,
+// so the engine can't throw away a tablebase win or draw by choosing a
+// move search alone thinks looks fine. A no-op whenever no table covers
+// this position -- including, today, whenever covering it would require
+// decoding a compressed on-disk block rather than the handful of built-in
+// basic endgames `tablebase::classify_basic` recognizes directly (see the
+// module comment).
+fn filter_root_moves_by_tablebase(data: &mut SearchData) {
+    if tablebase::probe_dtz(&data.pos).is_none() { return }
+
+    let ad = AttackData::new(&data.pos);
+    let undo = UndoState::undo_state(&data.pos);
+    let mut results = Vec::new();
+    for &m in data.constraints.searchmoves.iter() {
+        if !data.pos.pseudo_move_is_legal(m, &ad) { continue }
+        data.pos.do_move(m, &ad);
+        // The position is now from the opponent's point of view, so the
+        // result they see has to be inverted back to ours.
+        if let Some(wdl) = probe_wdl_with_rule50(data) {
+            results.push((m, tablebase::invert(wdl)));
+        }
+        data.pos.undo_move(m, &undo);
+    }
+    if results.is_empty() { return }
+
+    data.stats.tb_hits += 1;
+    let best = results.iter().map(|&(_, w)| tablebase::rank(w)).max().unwrap();
+    let keep: Vec<Move> = results.iter()
+        .filter(|&&(_, w)| tablebase::rank(w) == best)
+        .map(|&(m, _)| m)
+        .collect();
+    data.constraints.searchmoves.retain(|m| keep.contains(m));
+    data.root_moves.retain(|rm| keep.contains(&rm.m));
+}
+
+// Consults the Polyglot opening book for `data.pos` when `OwnBook` is set,
+// lazily loading `BookFile` the first time it's needed. Returns `None` (and
+// falls through to the normal search) whenever the book is off, empty for
+// this position, or would suggest an illegal move -- the last of which
+// shouldn't happen with a well-formed book, but a corrupt one shouldn't be
+// able to make the engine play an illegal move.
+fn book_move(data: &SearchData) -> Option<Move> {
+    if !options::own_book() {
+        return None;
+    }
+    if !book::is_loaded() && !book::load(&options::book_file()) {
+        return None;
+    }
+    // Deterministic rather than time-seeded, so weighted sampling is
+    // reproducible for a given position, which helps when debugging.
+    let mv = book::probe(&data.pos, options::best_book_move(), data.pos.hash() as u32);
+    if mv == NO_MOVE || !data.constraints.searchmoves.contains(&mv) {
+        return None;
+    }
+    Some(mv)
+}
+
 fn should_deepen(data: &SearchData) -> bool {
     if data.current_depth == MAX_PLY - 1 { return false }
     if data.state.load() == PONDERING_STATE { return true }
@@ -416,6 +874,15 @@ fn should_deepen(data: &SearchData) -> bool {
     if data.constraints.infinite { return true }
     if data.constraints.depth_limit < data.current_depth { return false }
     if !data.constraints.use_timer { return true }
+    // An easy move: the same best move and its first two replies for several
+    // iterations running, clearly ahead of the alternative. No point burning
+    // the rest of the soft limit confirming what's already obvious.
+    if effective_multi_pv() == 1 &&
+        data.easy_move.stable_iterations >= EASY_MOVE_STABLE_ITERATIONS &&
+        data.constraints.start_time.elapsed() >
+            data.constraints.soft_limit * EASY_MOVE_TIME_FRACTION.0 / EASY_MOVE_TIME_FRACTION.1 {
+        return false
+    }
     // If we're much more than halfway through our time, we won't make it
     // through the first move of the next iteration anyway.
     if data.constraints.start_time.elapsed() > data.constraints.soft_limit {
@@ -425,7 +892,7 @@ fn should_deepen(data: &SearchData) -> bool {
 }
 
 fn should_print(data: &SearchData) -> bool {
-    data.constraints.start_time.elapsed().as_secs() > 1
+    data.is_main && data.constraints.start_time.elapsed().as_secs() > 1
 }
 
 // print_pv_single prints the search data for a single root move.
@@ -463,6 +930,10 @@ fn print_pv_single(data: &SearchData, rm: &RootMove, ordinal: usize, alpha: Scor
 // print_pv prints out the most up-to-date information about the current
 // principal variations in the format expected by UCI.
 fn print_pv(data: &SearchData, alpha: Score, beta: Score) {
+    // Helper threads share everything about this search except the right to
+    // talk to the GUI; only the main thread's iteration gets printed.
+    if !data.is_main { return }
+
     // We need to print the n highest-scoring moves. They may not be in order
     // so we extract them in order with a heap.
     use std::collections::BinaryHeap;
@@ -473,7 +944,7 @@ fn print_pv(data: &SearchData, alpha: Score, beta: Score) {
         heap.push((rm.score, i));
     }
 
-    for i in 0..options::multi_pv() {
+    for i in 0..effective_multi_pv() {
         if let Some((_, idx)) = heap.pop() {
             print_pv_single(data, &data.root_moves[idx], i + 1, alpha, beta);
         } else {
@@ -482,10 +953,46 @@ fn print_pv(data: &SearchData, alpha: Score, beta: Score) {
     }
 }
 
+// The optimism bonus for the next iteration given this iteration's root
+// score: scales with how decisive the score is, saturating rather than
+// growing unboundedly as `prev` gets large.
+fn optimism_from_score(prev: Score) -> Score {
+    118 * prev / (prev.abs() + 169)
+}
+
+// The optimism bonus to mix into `lazy_score`/`static_eval` at a node `ply`
+// plies below the root. `data.optimism` is relative to whoever was on move
+// at the root, so it only applies as-is on even plies; odd plies (the
+// other side to move) get its negation.
+fn side_relative_optimism(data: &SearchData, ply: usize) -> Score {
+    if ply % 2 == 0 { data.optimism } else { -data.optimism }
+}
+
+// Lazy SMP depth-skipping tables (helper index `i` uses slot `(i-1) % 20`).
+// A helper skips root depth `d` whenever `((d + SKIP_PHASE[slot]) /
+// SKIP_SIZE[slot]) % 2 != 0`, which spreads 20 helpers across a diverse mix
+// of depth/phase combinations instead of all 20 redundantly replaying the
+// main thread's exact iteration sequence.
+const SKIP_SIZE: [usize; 20] =  [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+// Whether helper `helper_id` should skip iterative-deepening depth `depth`.
+// The main thread (id 0) never skips a depth.
+fn skip_iteration(helper_id: usize, depth: Depth) -> bool {
+    if helper_id == 0 { return false }
+    let slot = (helper_id - 1) % SKIP_SIZE.len();
+    ((depth + SKIP_PHASE[slot]) / SKIP_SIZE[slot]) % 2 != 0
+}
+
 fn deepening_search(data: &mut SearchData) {
     data.current_depth = 1;
+    data.optimism = 0;
     let (mut alpha, mut beta, mut last_score) = (score::MIN_SCORE, score::MAX_SCORE, 0);
     while should_deepen(data) {
+        if skip_iteration(data.helper_id, data.current_depth) {
+            data.current_depth += 1;
+            continue;
+        }
         if should_print(data) {
             println!("info depth {}", data.current_depth);
         }
@@ -493,7 +1000,7 @@ fn deepening_search(data: &mut SearchData) {
         let mut consecutive_fail_highs = 0;
         let mut consecutive_fail_lows = 0;
         const ASPIRE_MARGIN: [Score; 5] = [10, 35, 75, 300, 600];
-        if data.current_depth > 5 && options::multi_pv() == 1 {
+        if data.current_depth > 5 && effective_multi_pv() == 1 {
             alpha = max!(last_score - ASPIRE_MARGIN[0], score::MIN_SCORE);
             beta = min!(last_score + ASPIRE_MARGIN[0], score::MAX_SCORE);
         }
@@ -538,15 +1045,68 @@ fn deepening_search(data: &mut SearchData) {
             }
         }
 
+        update_easy_move(data);
+        data.optimism = optimism_from_score(last_score);
         data.current_depth += 1;
     }
 }
 
+// Computes the position two plies into the current best PV (the root move
+// plus the next move of its continuation), to stash in `EasyMoveManager`
+// for a future ponderhit check. Returns 0 (never a real Zobrist key) if
+// either move turns out not to be legal, e.g. because the PV is shorter
+// than two plies.
+fn easy_move_key(pos: &mut Position, m1: Move, m2: Move) -> u64 {
+    if m1 == NO_MOVE || m2 == NO_MOVE { return 0 }
+    let ad1 = AttackData::new(pos);
+    if !pos.pseudo_move_is_legal(m1, &ad1) { return 0 }
+    let undo1 = UndoState::undo_state(pos);
+    pos.do_move(m1, &ad1);
+
+    let ad2 = AttackData::new(pos);
+    let key = if pos.pseudo_move_is_legal(m2, &ad2) {
+        let undo2 = UndoState::undo_state(pos);
+        pos.do_move(m2, &ad2);
+        let key = pos.hash();
+        pos.undo_move(m2, &undo2);
+        key
+    } else {
+        0
+    };
+
+    pos.undo_move(m1, &undo1);
+    key
+}
+
+// Updates the easy-move record at the end of an iteration: if the best
+// root move's first three PV moves match what we saw last iteration (and
+// it's still clearly ahead of the second-best root move), bump the
+// stability counter; otherwise start over from this iteration's PV.
+fn update_easy_move(data: &mut SearchData) {
+    if data.root_moves.is_empty() { return }
+    let pv = [data.root_moves[0].m,
+              *data.root_moves[0].pv.get(0).unwrap_or(&NO_MOVE),
+              *data.root_moves[0].pv.get(1).unwrap_or(&NO_MOVE)];
+    let best_score = data.root_moves[0].score;
+    let ahead_by_margin = data.root_moves.len() < 2 ||
+        best_score - data.root_moves[1].score > EASY_MOVE_SCORE_MARGIN;
+
+    if ahead_by_margin && pv == data.easy_move.pv {
+        data.easy_move.stable_iterations += 1;
+    } else {
+        data.easy_move.key = easy_move_key(&mut data.pos, pv[0], pv[1]);
+        data.easy_move.pv = pv;
+        data.easy_move.stable_iterations = 0;
+    }
+}
+
 fn reduction(depth: SearchDepth,
              searched_moves: usize,
              searched_quiet_moves: usize,
              bad_move: bool,
-             special_move: bool) -> SearchDepth {
+             special_move: bool,
+             improving: bool,
+             cont_score: Score) -> SearchDepth {
     let mut r = 0.;
     if searched_moves > 2 || searched_quiet_moves > 0 {
         r = if searched_moves > 5 {
@@ -565,6 +1125,20 @@ fn reduction(depth: SearchDepth,
                 }
             }
         }
+        // A quiet move that has historically followed this continuation
+        // badly gets reduced further, on top of whatever `bad_move` already
+        // contributed from its own (non-continuation) history.
+        if cont_score < -4000 {
+            r += 1.;
+        } else if cont_score < -1000 {
+            r += 0.5;
+        }
+        // A position that isn't improving has less to lose from reducing
+        // further -- if it were going to pay off, the eval would likely
+        // already be trending up.
+        if !improving {
+            r += 1.;
+        }
         if special_move {
             r /= 2.;
         }
@@ -611,12 +1185,31 @@ fn search(data: &mut SearchData, ply: usize,
         beta = min!(beta, score::mate_in(ply + 1));
         if alpha >= beta { return alpha }
         if data.pos.is_draw() || ply >= MAX_PLY { return score::DRAW_SCORE }
+
+        if depth >= options::syzygy_probe_depth() as SearchDepth && data.pos.checkers() == 0 {
+            if let Some(wdl) = probe_wdl_with_rule50(data) {
+                data.stats.tb_hits += 1;
+                let tb_score = tablebase::wdl_to_score(wdl, ply);
+                let bound = match wdl {
+                    tablebase::Wdl::Win | tablebase::Wdl::CursedWin => score::AT_LEAST,
+                    tablebase::Wdl::Loss | tablebase::Wdl::BlessedLoss => score::AT_MOST,
+                    tablebase::Wdl::Draw => score::EXACT,
+                };
+                if (bound == score::AT_LEAST && tb_score >= beta) ||
+                    (bound == score::AT_MOST && tb_score <= alpha) ||
+                    bound == score::EXACT {
+                    data.tt.put(data.pos.hash(), NO_MOVE, depth, score_to_tt(tb_score, ply), bound);
+                    return tb_score;
+                }
+            }
+        }
     }
 
     let orig_alpha = alpha;
     let open_window = beta - alpha > 1;
 
     let (mut tt_move, mut tt_score, mut tt_score_type) = (NO_MOVE, score::MIN_SCORE, score::AT_MOST);
+    let mut tt_depth: u8 = 0;
     if root_node {
         tt_move = data.root_moves[0].m;
         tt_score = data.root_moves[0].score;
@@ -625,6 +1218,7 @@ fn search(data: &mut SearchData, ply: usize,
             tt_move = entry.m;
             tt_score = score_from_tt(entry.score as Score, ply);
             tt_score_type = entry.score_type;
+            tt_depth = entry.depth;
             if depth as u8 <= entry.depth {
                 if !open_window &&
                     ((tt_score >= beta && tt_score_type & score::AT_LEAST != 0) ||
@@ -635,21 +1229,38 @@ fn search(data: &mut SearchData, ply: usize,
         }
     }
 
-    let mut lazy_score = data.pos.psqt_score().interpolate(&data.pos);
+    // `lazy_score` is the optimism-biased baseline every pruning/margin
+    // decision below is measured against. `unbiased_score` tracks what it
+    // would be with the optimism term backed back out, so a score this
+    // function actually returns (rather than just compares against) never
+    // carries that bias upward into the parent node -- see
+    // `side_relative_optimism`.
+    let raw_score = data.pos.psqt_score().interpolate(&data.pos);
+    let mut lazy_score = raw_score + side_relative_optimism(data, ply);
+    let mut unbiased_score = raw_score;
     // TODO: write separate function to apply tt bounds.
     if data.pos.checkers() == 0 && tt_score != score::MIN_SCORE &&
         ((tt_score > lazy_score && tt_score_type & score::AT_LEAST != 0) ||
          (tt_score < lazy_score && tt_score_type & score::AT_MOST != 0)) {
         lazy_score = tt_score;
+        unbiased_score = tt_score;
     }
+    data.search_stack[ply].static_eval = lazy_score;
+    // Whether the side to move is doing better than it was two plies ago,
+    // i.e. the last time it was on move. Always false in check (the static
+    // eval isn't trustworthy there) and whenever there's no ply-2 history
+    // to compare against.
+    let improving = data.pos.checkers() == 0 && ply >= 2 &&
+        data.search_stack[ply - 2].static_eval > score::MIN_SCORE &&
+        lazy_score > data.search_stack[ply - 2].static_eval;
 
     if !root_node &&
         depth <= 5. &&
         data.pos.checkers() == 0 &&
         data.pos.non_pawn_material(data.pos.us()) != 0 &&
         (tt_move == NO_MOVE || tt_score > score::mated_in(MAX_PLY)) &&
-        lazy_score - 2 * futility_margin(depth) > beta {
-            return lazy_score - 2 * futility_margin(depth)
+        lazy_score - 2 * futility_margin(depth, improving) > beta {
+            return unbiased_score - 2 * futility_margin(depth, improving)
     }
 
     let depth_index = depth as usize;
@@ -687,8 +1298,8 @@ fn search(data: &mut SearchData, ply: usize,
 
     let margin = beta - lazy_score;
     if IID_ENABLED && tt_move == NO_MOVE &&
-        ((open_window && depth >= 5. && margin <= 300) ||
-         (!open_window && depth >= 8. && margin <= 150)) {
+        ((open_window && depth >= IID_PV_MIN_DEPTH && margin <= IID_PV_MARGIN) ||
+         (!open_window && depth >= IID_NON_PV_MIN_DEPTH && margin <= IID_NON_PV_MARGIN)) {
         let iid_depth = if open_window {
             (4. * depth / 5.) - 2.
         } else {
@@ -706,16 +1317,39 @@ fn search(data: &mut SearchData, ply: usize,
     let ad = AttackData::new(&data.pos);
     let undo = UndoState::undo_state(&data.pos);
 
+    // Singular extensions: if the tt_move is the only move that keeps the
+    // position at or above its own tt score, it's forced, and the line
+    // through it is extended a ply even though it's neither a check nor a
+    // deep pawn push.
+    let mut singular_move = NO_MOVE;
+    if !root_node &&
+        depth >= SINGULAR_MIN_DEPTH &&
+        tt_move != NO_MOVE &&
+        tt_score_type & score::AT_LEAST != 0 &&
+        tt_depth as SearchDepth >= depth - SINGULAR_TT_DEPTH_SLACK &&
+        !is_mate_score(tt_score) &&
+        !data.search_stack[ply].singular_search {
+        let beta_s = tt_score - singular_margin(depth);
+        let singular_depth = (depth - 1.) / 2.;
+        data.search_stack[ply].singular_search = true;
+        let mut failed_high = false;
+        let mut verify = MoveSelector::new(&data.pos, depth, &data.search_stack[ply], tt_move, data.countermove());
+        while let Some(m) = verify.next(&data.pos, &ad, &data.history) {
+            if m == tt_move || !data.pos.pseudo_move_is_legal(m, &ad) { continue }
+            data.pos.do_move(m, &ad);
+            data.search_stack[ply].played = m;
+            let score = -search(data, ply + 1, -beta_s, -beta_s + 1, singular_depth);
+            data.pos.undo_move(m, &undo);
+            if score >= beta_s { failed_high = true; break }
+        }
+        data.search_stack[ply].singular_search = false;
+        if !failed_high { singular_move = tt_move; }
+    }
+
     let mut selector = if root_node {
         MoveSelector::root(&data)
     } else {
-        // FIXME: countermoves is dire
-        let cm = if data.pos.last_move() == NO_MOVE || data.pos.last_move() == NULL_MOVE {
-            NO_MOVE
-        } else {
-            data.countermoves[data.pos.last_move().piece().index()][data.pos.last_move().to().index()]
-        };
-        MoveSelector::new(&data.pos, depth, &data.search_stack[ply], tt_move, cm)
+        MoveSelector::new(&data.pos, depth, &data.search_stack[ply], tt_move, data.countermove())
     };
 
     let mut searched_moves = 0;
@@ -734,17 +1368,24 @@ fn search(data: &mut SearchData, ply: usize,
 
         // gives_check is not precise, but it's just used for heuristic extensions.
         let gives_check = !m.is_castle() && !m.is_en_passant() &&
-            ((ad.potential_checks[m.piece().piece_type().index()] & bitboard::bb(m.to()) != 0) ||
-             (ad.check_discoverers & bitboard::bb(m.from()) != 0 &&
-              bitboard::ray(m.from(), m.to()) & bitboard::bb(ad.their_king) == 0));
+            (ad.potential_checks[m.piece().piece_type().index()].contains(m.to()) ||
+             (ad.check_discoverers.contains(m.from()) &&
+              (bitboard::ray(m.from(), m.to()) & bitboard::bb(ad.their_king)).is_empty()));
         let deep_pawn = m.piece().piece_type() == PieceType::Pawn &&
             (m.to().relative_to(data.pos.us()).rank().index() >= Rank::_7.index() &&
              (m.promote() == PieceType::NoPieceType || m.promote() == PieceType::Queen));
         let quiet_move = !m.is_capture() && m.promote() != PieceType::Queen;
-        let late_move = searched_moves > (depth * depth + 1.) as usize;
+        let late_move_threshold = if improving { depth * depth + 1. } else { depth * depth / 2. + 1. };
+        let late_move = searched_moves > late_move_threshold as usize;
 
         let mut see = selector.last_see();
-        let ext = if (gives_check || deep_pawn) && see_sign(&data.pos, m, &mut see) >= 0 { 1. } else { 0. };
+        let ext = if m == singular_move {
+            1.
+        } else if (gives_check || deep_pawn) && see_sign(&data.pos, m, &mut see) >= 0 {
+            1.
+        } else {
+            0.
+        };
 
         if !root_node &&
             ext == 0. &&
@@ -754,15 +1395,16 @@ fn search(data: &mut SearchData, ply: usize,
             m.promote() != PieceType::Queen &&
             best_score > score::mated_in(MAX_PLY) &&
             !selector.special_move() {
-            // History pruning.
-            // TODO: clean up the history interface; this is kind of ugly.
-            if quiet_move && depth <= 4. && data.history[SearchData::history_index(m)] < 0 {
+            // History pruning: skip quiet moves whose plain history plus
+            // continuation history (as a reply to the last two moves) is
+            // negative.
+            if quiet_move && depth <= 4. && data.combined_history(ply, m) < 0 {
                 continue
             }
 
             // Value/SEE pruning.
             if depth <= 5. &&
-                lazy_score + see_value(&data.pos, m, &mut see) + futility_margin(depth) <
+                lazy_score + see_value(&data.pos, m, &mut see) + futility_margin(depth, improving) <
                     alpha + 2 * searched_moves as Score {
                 continue
             }
@@ -778,8 +1420,9 @@ fn search(data: &mut SearchData, ply: usize,
 
         if !data.pos.pseudo_move_is_legal(m, &ad) { continue }
         data.pos.do_move(m, &ad);
+        data.search_stack[ply].played = m;
         let mut full_search = searched_moves == 0 ||
-                              (root_node && searched_moves <= options::multi_pv());
+                              (root_node && searched_moves <= effective_multi_pv());
         data.stats.nodes += 1;
         searched_moves += 1;
         let mut score = score::MIN_SCORE;
@@ -788,7 +1431,9 @@ fn search(data: &mut SearchData, ply: usize,
                                     searched_moves,
                                     searched_quiet_count,
                                     selector.bad_move() || see_sign(&data.pos, m, &mut see) < 0,
-                                    selector.special_move());
+                                    selector.special_move(),
+                                    improving,
+                                    data.combined_history(ply, m));
 
             if lmr_red >= 1. {
                 score = -search(data, ply + 1, -alpha - 1, -alpha, depth + ext - lmr_red - 1.);
@@ -830,7 +1475,7 @@ fn search(data: &mut SearchData, ply: usize,
                     data.root_moves[root_idx].pv.push(mv);
                 }
             }
-            if score > alpha && score < beta && searched_moves > options::multi_pv() {
+            if score > alpha && score < beta && searched_moves > effective_multi_pv() {
                 print_pv(data, alpha, beta)
             }
             debug_assert!(score_is_valid(data.root_moves[root_idx].score) || searched_moves > 0);
@@ -849,9 +1494,9 @@ fn search(data: &mut SearchData, ply: usize,
                         data.search_stack[ply].killers[1] = data.search_stack[ply].killers[0];
                         data.search_stack[ply].killers[0] = m;
                     }
-                    data.record_success(m, depth);
+                    data.record_success(ply, m, depth);
                     for i in 0..searched_quiet_count-1 {
-                        data.record_failure(searched_quiets[i], depth);
+                        data.record_failure(ply, searched_quiets[i], depth);
                     }
                 }
                 debug_assert!(score_is_valid(score));
@@ -884,6 +1529,14 @@ fn quiesce(data: &mut SearchData, ply: usize,
     if alpha >= beta { return alpha }
     if data.pos.is_draw() { return score::DRAW_SCORE }
     if ply >= MAX_PLY { return score::DRAW_SCORE }
+
+    if data.pos.checkers() == 0 {
+        if let Some(wdl) = probe_wdl_with_rule50(data) {
+            data.stats.tb_hits += 1;
+            return tablebase::wdl_to_score(wdl, ply);
+        }
+    }
+
     let open_window = beta - alpha > 1;
     let orig_alpha = alpha;
 
@@ -906,25 +1559,32 @@ fn quiesce(data: &mut SearchData, ply: usize,
     }
 
     let (mut best_move, mut best_score) = (NO_MOVE, score::MIN_SCORE);
-    let mut static_eval = eval::full(&data.pos);
+    // As in `search`, `static_eval` is the optimism-biased baseline used for
+    // the stand-pat/beta comparisons below and the per-move futility check
+    // further down, while `best_score` (what this function actually
+    // returns when nothing beats the stand pat) stays the raw, unbiased
+    // eval so the bias doesn't propagate upward into the parent node.
+    let raw_eval = eval::full(&data.pos);
+    let mut static_eval = raw_eval + side_relative_optimism(data, ply);
     debug_assert!(score_is_valid(static_eval));
     let in_check = data.pos.checkers() != 0;
     if !in_check {
-        best_score = static_eval;
-        if best_score >= alpha {
-            alpha = best_score;
+        best_score = raw_eval;
+        if static_eval >= alpha {
+            alpha = static_eval;
             if tt_score != score::MIN_SCORE &&
-                ((tt_score > best_score && tt_score_type & score::AT_LEAST != 0) ||
-                    (tt_score < best_score && tt_score_type & score::AT_MOST != 0)) {
+                ((tt_score > static_eval && tt_score_type & score::AT_LEAST != 0) ||
+                    (tt_score < static_eval && tt_score_type & score::AT_MOST != 0)) {
                 best_score = tt_score;
                 static_eval = tt_score;
             }
-            if best_score >= beta {
+            if static_eval >= beta {
                 debug_assert!(score_is_valid(best_score));
                 return beta;
             }
         }
     }
+    data.search_stack[ply].static_eval = static_eval;
 
     let ad = AttackData::new(&data.pos);
     let undo = UndoState::undo_state(&data.pos);
@@ -933,14 +1593,14 @@ fn quiesce(data: &mut SearchData, ply: usize,
     let mut selector = MoveSelector::new(&data.pos, depth, &data.search_stack[ply], tt_move, NO_MOVE);
     while let Some(m) = selector.next(&data.pos, &ad, &data.history) {
         let gives_check = !m.is_castle() && !m.is_en_passant() &&
-            ((ad.potential_checks[m.piece().piece_type().index()] & bitboard::bb(m.to()) != 0) ||
-             (ad.check_discoverers & bitboard::bb(m.from()) != 0 &&
-              bitboard::ray(m.from(), m.to()) & bitboard::bb(ad.their_king) == 0));
+            (ad.potential_checks[m.piece().piece_type().index()].contains(m.to()) ||
+             (ad.check_discoverers.contains(m.from()) &&
+              (bitboard::ray(m.from(), m.to()) & bitboard::bb(ad.their_king)).is_empty()));
         let see_value = data.pos.static_exchange_eval(m);
 
         if !gives_check && (!in_check || (!m.is_capture() && best_score > score::mated_in(MAX_PLY))) &&
             m.promote() != PieceType::Queen &&
-            static_eval + see_value + futility_margin(depth) < alpha {
+            static_eval + see_value + futility_margin(depth, true) < alpha {
             continue
         }
         if !in_check && see_value < 0 { continue }
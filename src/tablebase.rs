@@ -0,0 +1,206 @@
+// Syzygy endgame tablebase support.
+//
+// This tracks a `SyzygyPath` directory and the largest cardinality of table
+// present on disk, against the Syzygy file-naming convention (`KQvK.rtbw`,
+// `KRPvKR.rtbz`, etc), and is wired into `search`/`go` for real: root move
+// filtering, the in-search WDL cutoff, and the quiesce tablebase return all
+// call through to `probe_wdl`/`probe_dtz` below. What those probes can
+// actually answer today is narrower than "anything on disk", though:
+// decoding the compressed pairs/Huffman blocks inside a real `.rtbw`/`.rtbz`
+// file is a substantial project of its own (see the Fathom/pyffish probing
+// code for the scope), so that part of a probe still reports "no result".
+// `classify_basic` below covers the slice of material configurations simple
+// enough to resolve without reading a table at all -- bare kings, and a
+// lone king facing a king with one extra queen or rook -- so the wiring has
+// a real, live path instead of being scaffolding for a decoder that doesn't
+// exist yet.
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use board;
+use board::{Color, PieceType};
+use position::Position;
+use score::{self, Score};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+struct TablebaseState {
+    path: Option<PathBuf>,
+    max_cardinality: u32,
+}
+
+lazy_static! {
+    static ref STATE: Mutex<TablebaseState> = Mutex::new(TablebaseState {
+        path: None,
+        max_cardinality: 0,
+    });
+}
+
+// Parses the piece letters out of a Syzygy basename like "KQPvKR" (the part
+// before the first '.') and returns how many pieces it covers, so we can
+// track the largest cardinality of table actually present on disk without
+// having to open and parse every file up front.
+fn cardinality_of(stem: &str) -> u32 {
+    stem.chars().filter(|c| c.is_ascii_uppercase()).count() as u32
+}
+
+// Scans `path` for ".rtbw"/".rtbz" files and records the largest cardinality
+// seen, so `probe_wdl`/`probe_dtz` know whether a given position is small
+// enough to be covered by what's on disk.
+pub fn init(path: &str) {
+    let mut state = STATE.lock().unwrap();
+    state.path = Some(PathBuf::from(path));
+    state.max_cardinality = 0;
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let entry = match entry { Ok(e) => e, Err(_) => continue };
+        let file_name = entry.file_name();
+        let name = match file_name.to_str() { Some(n) => n, None => continue };
+        if !name.ends_with(".rtbw") && !name.ends_with(".rtbz") {
+            continue;
+        }
+        let stem = &name[..name.len() - 5];
+        state.max_cardinality = ::std::cmp::max(state.max_cardinality, cardinality_of(stem));
+    }
+}
+
+pub fn max_cardinality() -> u32 {
+    STATE.lock().unwrap().max_cardinality
+}
+
+fn is_loaded(pos: &Position, cardinality: u32) -> bool {
+    STATE.lock().unwrap().path.is_some() && pos.piece_count() <= cardinality
+}
+
+// Classifies the handful of material configurations whose WDL result is
+// knowable directly from the pieces on the board, with no table to read at
+// all: bare kings (always a draw), and a lone king facing a king plus a
+// single extra queen or rook (always winning for the side with the extra
+// piece -- barring the rare literal stalemate trap, not worth special-
+// casing for a result this coarse). Anything else -- including every
+// pawn ending, which needs real opposition/key-square logic rather than a
+// material count -- is `None`, the same as a position too large for
+// whatever's loaded from disk.
+fn classify_basic(pos: &Position) -> Option<Wdl> {
+    let mut counts = [0u32; 2];
+    let mut extra = [PieceType::NoPieceType; 2];
+    for sq in board::each_square() {
+        let piece = pos.piece_on(sq);
+        let pt = piece.piece_type();
+        if pt == PieceType::NoPieceType || pt == PieceType::King {
+            continue;
+        }
+        let side = if piece.color() == Color::White { 0 } else { 1 };
+        counts[side] += 1;
+        extra[side] = pt;
+    }
+
+    let winner = match (counts[0], counts[1]) {
+        (0, 0) => return Some(Wdl::Draw),
+        (1, 0) if extra[0] == PieceType::Queen || extra[0] == PieceType::Rook => Color::White,
+        (0, 1) if extra[1] == PieceType::Queen || extra[1] == PieceType::Rook => Color::Black,
+        _ => return None,
+    };
+    Some(if pos.us() == winner { Wdl::Win } else { Wdl::Loss })
+}
+
+// Probes the win/draw/loss table for `pos`: first the built-in basic
+// endgames `classify_basic` can resolve outright, then whatever's actually
+// loaded from disk -- which, per the module comment, is nothing yet, since
+// decoding a real compressed block isn't implemented. The cardinality check
+// is left in place since it's the condition a real decode will need to
+// guard on.
+pub fn probe_wdl(pos: &Position) -> Option<Wdl> {
+    if let Some(wdl) = classify_basic(pos) {
+        return Some(wdl);
+    }
+    if !is_loaded(pos, max_cardinality()) {
+        return None;
+    }
+    None
+}
+
+// Probes the distance-to-zero table for `pos`, returning a signed ply count
+// to the next zeroing move (negative for the side to move losing). Callers
+// only use this as an existence check before consulting `probe_wdl` (see
+// `filter_root_moves_by_tablebase`), so the basic endgames below report a
+// placeholder `0` rather than a real distance -- same decoding caveat as
+// `probe_wdl` for anything that would need a real file read.
+pub fn probe_dtz(pos: &Position) -> Option<i32> {
+    if classify_basic(pos).is_some() {
+        return Some(0);
+    }
+    if !is_loaded(pos, max_cardinality()) {
+        return None;
+    }
+    None
+}
+
+// Ranks a WDL result from the perspective of the side it's reported for,
+// best outcome first. Used at the root to find the best result any legal
+// move can achieve and keep only the moves that match it.
+pub fn rank(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 2,
+        Wdl::CursedWin => 1,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -1,
+        Wdl::Loss => -2,
+    }
+}
+
+// Flips a WDL result to the other side's perspective.
+pub fn invert(wdl: Wdl) -> Wdl {
+    match wdl {
+        Wdl::Win => Wdl::Loss,
+        Wdl::CursedWin => Wdl::BlessedLoss,
+        Wdl::Draw => Wdl::Draw,
+        Wdl::BlessedLoss => Wdl::CursedWin,
+        Wdl::Loss => Wdl::Win,
+    }
+}
+
+// Maps a WDL result to the engine's mate-distance score scale, offset by
+// `ply` the same way `score_from_tt`/mate scores are, and nudged just
+// inside the mate bound so tablebase wins/losses still sort behind genuine
+// mates found by search.
+pub fn wdl_to_score(wdl: Wdl, ply: usize) -> Score {
+    match wdl {
+        Wdl::Win | Wdl::CursedWin => score::mate_in(ply + 2) - 1,
+        Wdl::Loss | Wdl::BlessedLoss => score::mated_in(ply + 2) + 1,
+        Wdl::Draw => score::DRAW_SCORE,
+    }
+}
+
+// A cursed win/blessed loss is only a threat once the fifty-move counter
+// has run long enough that the rule could plausibly expire the game before
+// the side with the advantage finishes converting it. Below this point the
+// sharper result still steers search correctly, since there's ample room
+// left to convert.
+const CURSE_THRESHOLD: u8 = 80;
+
+// Folds a cursed win or blessed loss down to a plain draw once `UseRule50`
+// is on and `halfmove_clock` has crossed `CURSE_THRESHOLD`, so the engine
+// stops chasing a result the fifty-move rule is about to erase. Leaves
+// plain `Win`/`Loss`/`Draw` untouched, and is a no-op entirely when
+// `use_rule50` is false.
+pub fn adjust_for_rule50(wdl: Wdl, halfmove_clock: u8, use_rule50: bool) -> Wdl {
+    if !use_rule50 || halfmove_clock < CURSE_THRESHOLD {
+        return wdl;
+    }
+    match wdl {
+        Wdl::CursedWin | Wdl::BlessedLoss => Wdl::Draw,
+        other => other,
+    }
+}
@@ -0,0 +1,182 @@
+// Shared transposition table for Lazy SMP search.
+//
+// Every slot is a few plain `AtomicU64` words rather than a mutex-guarded
+// struct, so `get`/`put` from different search threads never block each
+// other even when they hash to the same slot -- the actual requirement for
+// sharing one `Table` behind an `Arc` across helper threads on a hot path.
+// Lock-freedom means a `put` racing a `get` (or another `put`) on the same
+// slot can tear: the reader might observe half of an old write and half of
+// a new one. Rather than track torn fields individually, each slot stores a
+// tag word holding the position's hash XORed with the slot's other words,
+// and `get` only trusts what it reads once XORing all three back together
+// reproduces the hash it's probing for -- any torn combination of words fails that check
+// and is treated as a miss, the same self-checking trick Stockfish-style
+// lockless tables use.
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use movement::{Move, NO_MOVE};
+use score::Score;
+
+// `Move` has to round-trip through a plain `u64` to live in an atomic slot.
+// It's `Copy` (see its use inline in `[[Move; 64]; 16]` tables elsewhere),
+// which is only true of small, pointer-free data, so treating it as raw
+// bytes and zero-extending into a `u64` is sound as long as it actually
+// fits -- checked once here rather than trusted silently.
+const _MOVE_FITS_IN_U64: () = [()][(mem::size_of::<Move>() > mem::size_of::<u64>()) as usize];
+
+fn move_to_bits(m: Move) -> u64 {
+    let mut bits = 0u64;
+    unsafe {
+        ::std::ptr::copy_nonoverlapping(
+            &m as *const Move as *const u8,
+            &mut bits as *mut u64 as *mut u8,
+            mem::size_of::<Move>(),
+        );
+    }
+    bits
+}
+
+fn bits_to_move(bits: u64) -> Move {
+    unsafe { ::std::ptr::read(&bits as *const u64 as *const Move) }
+}
+
+// The non-key fields of an entry, packed into one word: `depth` and
+// `score_type` each fit comfortably in a byte, `score` in an `i16`, and
+// `generation` in the byte left over.
+fn pack_payload(depth: u8, score: i16, score_type: u8, generation: u8) -> u64 {
+    (depth as u64) |
+        ((score as u16 as u64) << 8) |
+        ((score_type as u64) << 24) |
+        ((generation as u64) << 32)
+}
+
+fn unpack_payload(bits: u64) -> (u8, i16, u8, u8) {
+    let depth = bits as u8;
+    let score = (bits >> 8) as u16 as i16;
+    let score_type = (bits >> 24) as u8;
+    let generation = (bits >> 32) as u8;
+    (depth, score, score_type, generation)
+}
+
+#[derive(Copy, Clone)]
+pub struct Entry {
+    pub key: u64,
+    pub m: Move,
+    pub depth: u8,
+    pub score: i16,
+    pub score_type: u8,
+    pub generation: u8,
+}
+
+// One lockless slot: the move and the packed payload each live in their own
+// word, and `tag` holds both of them XORed together with the key, so `get`
+// can tell a genuine hit from a torn read across any combination of the
+// three words.
+struct Slot {
+    move_bits: AtomicU64,
+    payload: AtomicU64,
+    tag: AtomicU64,
+}
+
+impl Slot {
+    fn empty() -> Slot {
+        Slot {
+            move_bits: AtomicU64::new(move_to_bits(NO_MOVE)),
+            payload: AtomicU64::new(0),
+            tag: AtomicU64::new(move_to_bits(NO_MOVE)),
+        }
+    }
+
+    fn load(&self) -> Option<Entry> {
+        // Relaxed is enough: the tag check below is what establishes
+        // whether these three loads saw a consistent write, not memory
+        // ordering between threads.
+        let move_bits = self.move_bits.load(Ordering::Relaxed);
+        let payload = self.payload.load(Ordering::Relaxed);
+        let tag = self.tag.load(Ordering::Relaxed);
+        let key = tag ^ move_bits ^ payload;
+        if key == 0 {
+            return None;
+        }
+        let (depth, score, score_type, generation) = unpack_payload(payload);
+        Some(Entry { key, m: bits_to_move(move_bits), depth, score, score_type, generation })
+    }
+
+    fn store(&self, key: u64, m: Move, depth: u8, score: i16, score_type: u8, generation: u8) {
+        let move_bits = move_to_bits(m);
+        let payload = pack_payload(depth, score, score_type, generation);
+        let tag = key ^ move_bits ^ payload;
+        self.move_bits.store(move_bits, Ordering::Relaxed);
+        self.payload.store(payload, Ordering::Relaxed);
+        self.tag.store(tag, Ordering::Relaxed);
+    }
+}
+
+pub struct Table {
+    slots: Vec<Slot>,
+    generation: AtomicU64,
+}
+
+// Largest power of two that's still `<= n`, used to round the configured
+// hash size down to a bucket count rather than `next_power_of_two`'s
+// round-up -- rounding up can silently allocate up to nearly twice the
+// requested memory whenever `n` isn't itself already a power of two.
+fn prev_power_of_two(n: usize) -> usize {
+    if n <= 1 { return 1; }
+    1usize << (63 - (n as u64).leading_zeros())
+}
+
+impl Table {
+    // `size_bytes` is the UCI `Hash` size in bytes; rounded down to a power
+    // of two slot count (each slot is three `AtomicU64` words) so `index`
+    // can mask instead of dividing.
+    pub fn new(size_bytes: usize) -> Table {
+        let slot_size = mem::size_of::<Slot>();
+        let entries = (size_bytes / slot_size).max(1);
+        let count = prev_power_of_two(entries);
+        Table {
+            slots: (0..count).map(|_| Slot::empty()).collect(),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash as usize) & (self.slots.len() - 1)
+    }
+
+    // Returns the entry stored for `hash`, or `None` if the slot it maps to
+    // holds a different position's key, nothing's been stored there yet, or
+    // a concurrent `put` tore the slot mid-read.
+    pub fn get(&self, hash: u64) -> Option<Entry> {
+        match self.slots[self.index(hash)].load() {
+            Some(entry) if entry.key == hash => Some(entry),
+            _ => None,
+        }
+    }
+
+    // Stores a search result for `hash`, replacing whatever was in its slot
+    // unless that entry is for the same position, from the current
+    // generation, and searched deeper -- so a shallow re-probe from one helper thread can't
+    // evict a deeper result another thread just found.
+    // `depth` takes the engine's fractional search depth (see
+    // `search::SearchDepth`) directly, rounding down to the `u8` the table
+    // stores entries at.
+    pub fn put(&self, hash: u64, m: Move, depth: f32, score: Score, score_type: u8) {
+        let generation = self.generation.load(Ordering::Relaxed) as u8;
+        let depth = depth as u8;
+        let slot = &self.slots[self.index(hash)];
+        if let Some(entry) = slot.load() {
+            if entry.key == hash && entry.generation == generation && entry.depth > depth {
+                return;
+            }
+        }
+        slot.store(hash, m, depth, score as i16, score_type, generation);
+    }
+
+    // Marks the start of a new search so `put` stops protecting entries
+    // left over from the previous `go()`.
+    pub fn new_generation(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+}